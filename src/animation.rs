@@ -0,0 +1,224 @@
+//! Tile animations authored in Tiled with `<animation>` frames
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// the same xml-rs that tiled reads maps with
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+/// One frame of a tile animation
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frame {
+    // global id of the tile shown during the frame
+    gid: u32,
+    // how long the frame is shown for in milliseconds
+    duration: u32,
+}
+
+impl Frame {
+    /// Creates a new frame
+    pub fn new(gid: u32, duration: u32) -> Frame {
+        Frame { gid, duration }
+    }
+
+    // Getters
+    pub fn get_gid(&self) -> u32 {
+        self.gid
+    }
+
+    pub fn get_duration(&self) -> u32 {
+        self.duration
+    }
+}
+
+/// The frames an animated tile loops through
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileAnimation {
+    frames: Vec<Frame>,
+    // the length of one loop in milliseconds
+    length: u64,
+}
+
+impl TileAnimation {
+    /// Creates an animation that plays the frames in order
+    pub fn new(frames: Vec<Frame>) -> TileAnimation {
+        let length = frames.iter().map(|frame| frame.duration as u64).sum();
+
+        TileAnimation { frames, length }
+    }
+
+    // Getters
+    pub fn get_frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn get_length(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns the gid of the frame showing after time milliseconds
+    pub fn frame_at(&self, time: u64) -> Option<u32> {
+        if self.length == 0 {
+            return self.frames.first().map(|frame| frame.gid);
+        }
+
+        let mut time = time % self.length;
+        for frame in &self.frames {
+            if time < frame.duration as u64 {
+                return Some(frame.gid);
+            }
+            time -= frame.duration as u64;
+        }
+
+        None
+    }
+}
+
+/// Reads every tile animation of a map, keyed by the global id of the
+/// animated tile. External tilesets are loaded relative to directory
+pub fn parse_animations<R: Read>(
+    reader: R,
+    directory: &Path,
+) -> Result<HashMap<u32, TileAnimation>, String> {
+    let mut animations = HashMap::new();
+    collect_animations(reader, 0, directory, &mut animations)?;
+    Ok(animations)
+}
+
+/// Walks a map or tileset document adding the animations it finds. first_gid
+/// is used for external tilesets, which do not know their own first gid
+fn collect_animations<R: Read>(
+    reader: R,
+    first_gid: u32,
+    directory: &Path,
+    animations: &mut HashMap<u32, TileAnimation>,
+) -> Result<(), String> {
+    let mut first_gid = first_gid;
+    let mut tile = None;
+    let mut frames = vec![];
+
+    for event in EventReader::new(reader) {
+        match event.map_err(|error| error.to_string())? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => match name.local_name.as_str() {
+                "tileset" => {
+                    if let Some(gid) = attribute(&attributes, "firstgid")? {
+                        first_gid = gid;
+                    }
+
+                    if let Some(source) = find(&attributes, "source") {
+                        let path = directory.join(source);
+                        let file =
+                            File::open(&path).map_err(|error| format!("{:?}: {}", path, error))?;
+                        let directory = path.parent().unwrap_or(directory);
+                        collect_animations(file, first_gid, directory, animations)?;
+                    }
+                }
+                "tile" => {
+                    tile = attribute(&attributes, "id")?;
+                    frames.clear();
+                }
+                "frame" => {
+                    let tile_id = attribute(&attributes, "tileid")?;
+                    let duration = attribute(&attributes, "duration")?;
+
+                    match (tile_id, duration) {
+                        (Some(tile_id), Some(duration)) => {
+                            frames.push(Frame::new(first_gid + tile_id, duration))
+                        }
+                        _ => return Err("a frame must have a tileid and duration".to_string()),
+                    }
+                }
+                _ => (),
+            },
+            XmlEvent::EndElement { ref name } if name.local_name == "tile" => {
+                if let Some(id) = tile.take() {
+                    if !frames.is_empty() {
+                        let animation = TileAnimation::new(frames.split_off(0));
+                        animations.insert(first_gid + id, animation);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Looks up an attribute by name
+fn find<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.name.local_name == name)
+        .map(|attribute| attribute.value.as_str())
+}
+
+/// Looks up a numeric attribute by name
+fn attribute(attributes: &[OwnedAttribute], name: &str) -> Result<Option<u32>, String> {
+    match find(attributes, name) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{} should be a number, found {:?}", name, value)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANIMATED_MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.0" orientation="orthogonal" width="1" height="1" tilewidth="16" tileheight="16">
+ <tileset firstgid="1" name="flowers" tilewidth="16" tileheight="16">
+  <image source="flowers.png" width="64" height="16"/>
+  <tile id="0">
+   <animation>
+    <frame tileid="0" duration="100"/>
+    <frame tileid="1" duration="300"/>
+   </animation>
+  </tile>
+  <tile id="2"/>
+ </tileset>
+ <tileset firstgid="5" name="water" tilewidth="16" tileheight="16">
+  <image source="water.png" width="48" height="16"/>
+  <tile id="1">
+   <animation>
+    <frame tileid="1" duration="250"/>
+    <frame tileid="2" duration="250"/>
+    <frame tileid="0" duration="250"/>
+   </animation>
+  </tile>
+ </tileset>
+</map>"#;
+
+    #[test]
+    fn animations_are_keyed_by_gid() {
+        let animations = parse_animations(ANIMATED_MAP.as_bytes(), Path::new(".")).unwrap();
+
+        assert_eq!(animations.len(), 2);
+        assert_eq!(
+            animations[&1].get_frames(),
+            &[Frame::new(1, 100), Frame::new(2, 300)]
+        );
+        assert_eq!(animations[&6].get_length(), 750);
+        assert_eq!(animations[&6].get_frames()[2], Frame::new(5, 250));
+    }
+
+    #[test]
+    fn frames_loop_over_time() {
+        let animation = TileAnimation::new(vec![Frame::new(1, 100), Frame::new(2, 300)]);
+
+        assert_eq!(animation.frame_at(0), Some(1));
+        assert_eq!(animation.frame_at(99), Some(1));
+        assert_eq!(animation.frame_at(100), Some(2));
+        assert_eq!(animation.frame_at(399), Some(2));
+        assert_eq!(animation.frame_at(400), Some(1));
+        assert_eq!(animation.frame_at(1150), Some(2));
+    }
+}
@@ -1,25 +1,34 @@
 //! Represents a camera that keeps track of information regarding what is displayed
 
-use glium::*;
-
-use map::*;
-use std::collections::HashMap;
-
 pub struct Camera {
     // x and y coordinates for determining the location on the map
-    // these are representative of the bottom left corner of the display
+    // these are representative of the top left corner of the display
     pos_x: i32,
     pos_y: i32,
     width: i32,
     height: i32,
 
-    // map borders
+    // map borders, the largest pos_x and pos_y that keep the view on the map
     x_max: i32,
     y_max: i32,
 
+    // size of the map in pixels, None leaves the camera free to go anywhere
+    bounds: Option<(i32, i32)>,
+
+    // the point the camera is centred on, kept as floats so smoothing
+    // can move it by less than a pixel each update
+    focus_x: f64,
+    focus_y: f64,
+
+    // the target can move this far around the centre before the camera follows
+    deadzone_width: i32,
+    deadzone_height: i32,
+
+    // how quickly the camera catches up with the target, 0 snaps straight to it
+    smoothing: f64,
+
     // buffers
     tile_buffer: Vec<Tile>,
-    map_buffer: HashMap<String, Map>,
 }
 
 impl Camera {
@@ -48,20 +57,24 @@ impl Camera {
         self.y_max
     }
 
-    pub fn get_tile_buffer(&self) -> &Vec<Tile> {
-        &self.tile_buffer
+    pub fn get_bounds(&self) -> Option<(i32, i32)> {
+        self.bounds
     }
 
-    pub fn get_mut_tile_buffer(&mut self) -> &Vec<Tile> {
-        &self.tile_buffer
+    pub fn get_deadzone(&self) -> (i32, i32) {
+        (self.deadzone_width, self.deadzone_height)
     }
 
-    pub fn get_map_buffer(&self) -> &HashMap<String, Map> {
-        &self.map_buffer
+    pub fn get_smoothing(&self) -> f64 {
+        self.smoothing
     }
 
-    pub fn get_mut_map_buffer(&mut self) -> &HashMap<String, Map> {
-        &self.map_buffer
+    pub fn get_tile_buffer(&self) -> &Vec<Tile> {
+        &self.tile_buffer
+    }
+
+    pub fn get_mut_tile_buffer(&mut self) -> &mut Vec<Tile> {
+        &mut self.tile_buffer
     }
 
     pub fn get_rect(&self) -> (i32, i32, i32, i32) {
@@ -70,36 +83,35 @@ impl Camera {
 
     /// loads a camera with with no known values
     pub fn new() -> Camera {
-        Camera {
-            pos_x: 0,
-            pos_y: 0,
-            width: 0,
-            height: 0,
-            x_max: 0,
-            y_max: 0,
-            tile_buffer: vec![],
-            map_buffer: HashMap::new(),
-        }
+        Camera::load(0, 0, 0, 0)
     }
 
     /// loads a camera from already known values
     pub fn load(pos_x: i32, pos_y: i32, width: i32, height: i32) -> Camera {
-        let x_max = pos_x + width;
-        let y_max = pos_y + height;
-
         Camera {
             pos_x,
             pos_y,
             width,
             height,
-            x_max,
-            y_max,
+            x_max: i32::MAX,
+            y_max: i32::MAX,
+            bounds: None,
+            focus_x: (pos_x + width / 2) as f64,
+            focus_y: (pos_y + height / 2) as f64,
+            deadzone_width: 0,
+            deadzone_height: 0,
+            smoothing: 0.0,
             tile_buffer: vec![],
-            map_buffer: HashMap::new(),
         }
     }
 }
 
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera::new()
+    }
+}
+
 impl Camera {
     /// sets pos_x
     pub fn pos_x(&mut self, x: i32) {
@@ -121,25 +133,122 @@ impl Camera {
         self.height = h;
     }
 
+    /// Keeps the camera inside a map of this size in pixels
+    pub fn bounds(&mut self, map_width: i32, map_height: i32) {
+        self.bounds = Some((map_width, map_height));
+        self.x_max();
+        self.y_max();
+        self.clamp();
+    }
+
+    /// Lets the camera go anywhere again
+    pub fn unbounded(&mut self) {
+        self.bounds = None;
+        self.x_max();
+        self.y_max();
+    }
+
+    /// sets how far the target can move from the centre of the view before
+    /// the camera starts to follow it
+    pub fn deadzone(&mut self, w: i32, h: i32) {
+        self.deadzone_width = w;
+        self.deadzone_height = h;
+    }
+
+    /// sets how quickly the camera catches up with its target. Higher is
+    /// faster and 0 turns smoothing off
+    pub fn smoothing(&mut self, rate: f64) {
+        self.smoothing = rate;
+    }
+
     /// calculates x_max
     pub fn x_max(&mut self) {
-        self.x_max = self.pos_x + self.width;
+        self.x_max = match self.bounds {
+            Some((map_width, _)) => limits(self.width, map_width).1,
+            None => i32::MAX,
+        };
     }
 
     /// calculates y_max
     pub fn y_max(&mut self) {
-        self.y_max = self.pos_y + self.height;
+        self.y_max = match self.bounds {
+            Some((_, map_height)) => limits(self.height, map_height).1,
+            None => i32::MAX,
+        };
+    }
+
+    /// Moves the camera back onto the map. Maps smaller than the view are
+    /// centred instead
+    pub fn clamp(&mut self) {
+        if let Some((map_width, map_height)) = self.bounds {
+            let (x_min, x_max) = limits(self.width, map_width);
+            let (y_min, y_max) = limits(self.height, map_height);
+
+            self.pos_x = self.pos_x.clamp(x_min, x_max);
+            self.pos_y = self.pos_y.clamp(y_min, y_max);
+        }
+    }
+
+    /// Centres the view on a point straight away, ie after warping to
+    /// another map
+    pub fn center_on(&mut self, x: f64, y: f64) {
+        self.focus_x = x;
+        self.focus_y = y;
+        self.look_at_focus();
+    }
+
+    /// Moves the view towards a target that has moved, respecting the
+    /// deadzone and smoothing. dt is the time since the last update in seconds
+    pub fn follow(&mut self, x: f64, y: f64, dt: f64) {
+        // the camera only has to move far enough to get the target back
+        // inside the deadzone
+        let half_w = self.deadzone_width as f64 / 2.0;
+        let half_h = self.deadzone_height as f64 / 2.0;
+        let goal_x = self.focus_x.clamp(x - half_w, x + half_w);
+        let goal_y = self.focus_y.clamp(y - half_h, y + half_h);
+
+        if self.smoothing > 0.0 {
+            // framerate independent easing towards the goal
+            let t = 1.0 - (-self.smoothing * dt).exp();
+            self.focus_x += (goal_x - self.focus_x) * t;
+            self.focus_y += (goal_y - self.focus_y) * t;
+        } else {
+            self.focus_x = goal_x;
+            self.focus_y = goal_y;
+        }
+
+        self.look_at_focus();
+    }
+
+    /// Puts the view around the focus point, then pulls the focus back with
+    /// the view if it had to be clamped so the camera does not lag behind
+    /// when leaving the edge of the map
+    fn look_at_focus(&mut self) {
+        let x = (self.focus_x - self.width as f64 / 2.0).round() as i32;
+        let y = (self.focus_y - self.height as f64 / 2.0).round() as i32;
+
+        self.pos_x = x;
+        self.pos_y = y;
+        self.clamp();
+
+        if self.pos_x != x {
+            self.focus_x = (self.pos_x + self.width / 2) as f64;
+        }
+        if self.pos_y != y {
+            self.focus_y = (self.pos_y + self.height / 2) as f64;
+        }
     }
 
     /// clears the tile_buffer
     pub fn clear_tile_buffer(&mut self) {
-        &self.tile_buffer.clear();
+        self.tile_buffer.clear();
     }
 
-    /// reserves an estimated amount of space
+    /// reserves an estimated amount of space, every tile takes two
+    /// triangles so six vertices
     pub fn tile_buffer_auto_reserve(&mut self) {
         self.tile_buffer
-            .reserve(((self.width / 16) * (self.height / 16)) as usize)
+            .reserve(((self.width / 16 + 1) * (self.height / 16 + 1) * 6) as usize)
     }
 
     /// reserves specific amounts of space in the buffer
@@ -162,36 +271,122 @@ impl Camera {
         self.tile_buffer.push(tile);
     }
 
-    /// Adds a map to the map_buffer
-    pub fn push_to_map_buffer(&mut self, name: String, map: Map) {
-        self.map_buffer.insert(name, map);
-    }
-
     pub fn resize(&mut self, w: i32, h: i32) {
-        //self.width(w);
-        //self.height(h);
-
         self.width(w);
         self.height(h);
         self.x_max();
         self.y_max();
+        self.look_at_focus();
     }
 }
 
+/// The smallest and largest position that keeps a view on the map. When the
+/// map is smaller than the view both are the position that centres it
+fn limits(view: i32, map: i32) -> (i32, i32) {
+    if map <= view {
+        let centre = (map - view) / 2;
+        (centre, centre)
+    } else {
+        (0, map - view)
+    }
+}
+
+/// One corner of a tile as it is sent to the GPU
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Tile {
-    tile_rect: [f64; 4],
-    tex_rect: [f64; 2],
+    // where the corner sits on the map in pixels
+    position: [f32; 2],
+    // where the corner sits in the tile sheet, from 0 to 1
+    tex_coords: [f32; 2],
 }
 
-implement_vertex!(Tile, tile_rect, tex_rect);
+// glium's vertex macro still uses mem::uninitialized internally
+#[allow(deprecated)]
+mod vertex {
+    use super::Tile;
+
+    implement_vertex!(Tile, position, tex_coords);
+}
 
 impl Tile {
-    /// Does some math so the coordinates are translated correctly
-    pub fn tex_rect(&mut self, t_width: i32, t_height: i32, x_camera: i32, y_camera: i32) {
-        self.tex_rect = [
-            self.tile_rect[0] * t_width as f64 - x_camera as f64,
-            self.tile_rect[1] * t_height as f64 - y_camera as f64,
-        ];
+    pub fn new(position: [f32; 2], tex_coords: [f32; 2]) -> Tile {
+        Tile {
+            position,
+            tex_coords,
+        }
+    }
+
+    // Getters
+    pub fn get_position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    pub fn get_tex_coords(&self) -> [f32; 2] {
+        self.tex_coords
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_to_the_map() {
+        let mut camera = Camera::load(0, 0, 320, 240);
+        camera.bounds(1000, 800);
+        assert_eq!((camera.get_x_max(), camera.get_y_max()), (680, 560));
+
+        camera.pos_x(900);
+        camera.pos_y(-50);
+        camera.clamp();
+        assert_eq!(camera.get_rect(), (680, 0, 320, 240));
+
+        // the bottom right corner of the map
+        camera.center_on(1000.0, 800.0);
+        assert_eq!((camera.get_x(), camera.get_y()), (680, 560));
+    }
+
+    #[test]
+    fn small_maps_are_centred() {
+        let mut camera = Camera::load(0, 0, 320, 240);
+        camera.bounds(128, 400);
+
+        camera.center_on(0.0, 0.0);
+        assert_eq!((camera.get_x(), camera.get_y()), (-96, 0));
+
+        camera.center_on(128.0, 400.0);
+        assert_eq!((camera.get_x(), camera.get_y()), (-96, 160));
+        assert_eq!(camera.get_x_max(), -96);
+    }
+
+    #[test]
+    fn follows_outside_the_deadzone() {
+        let mut camera = Camera::load(0, 0, 100, 100);
+        camera.deadzone(20, 20);
+        camera.center_on(50.0, 50.0);
+
+        // moving inside the deadzone leaves the camera alone
+        camera.follow(58.0, 45.0, 0.1);
+        assert_eq!((camera.get_x(), camera.get_y()), (0, 0));
+
+        // leaving it drags the camera just far enough to catch up
+        camera.follow(70.0, 50.0, 0.1);
+        assert_eq!((camera.get_x(), camera.get_y()), (10, 0));
+    }
+
+    #[test]
+    fn smoothing_eases_towards_the_target() {
+        let mut camera = Camera::load(0, 0, 100, 100);
+        camera.smoothing(10.0);
+        camera.center_on(50.0, 50.0);
+
+        camera.follow(150.0, 50.0, 0.1);
+        let x = camera.get_x();
+        assert!(x > 0 && x < 100, "{}", x);
+
+        for _ in 0..100 {
+            camera.follow(150.0, 50.0, 0.1);
+        }
+        assert_eq!(camera.get_x(), 100);
     }
 }
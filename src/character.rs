@@ -0,0 +1,582 @@
+//! The characters travelling in the party, with what they have equipped and
+//! the stats that come out of it
+
+use equipment::{EquipError, EquipSlot, Equipment};
+use inventory::Inventory;
+use items::{ItemId, ItemRegistry};
+use stats::{Duration, Modifier, Stat, Stats};
+use status::{Status, StatusTracker};
+
+/// Poisoned characters lose this much of their Hp stat each turn, ie 8 for
+/// an eighth
+const POISON_FRACTION: i32 = 8;
+
+/// A member of the party
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Character {
+    name: String,
+    level: u32,
+    // hit points and psychic points left, out of the Hp and Pp stats
+    hp: i32,
+    pp: i32,
+    // stats at level 1, before anything is equipped
+    base: Stats,
+    // what each level after the first adds to the base stats
+    growth: Stats,
+    equipment: Equipment,
+    // raise or lower stats until they wear off
+    modifiers: Vec<Modifier>,
+    statuses: StatusTracker,
+    // what the equipment adds to each stat, worked out again by recompute
+    #[serde(skip)]
+    bonuses: Stats,
+}
+
+impl Character {
+    /// Creates a level 1 character with nothing equipped and every stat at 0
+    pub fn new(name: &str) -> Character {
+        Character {
+            name: name.to_string(),
+            level: 1,
+            hp: 0,
+            pp: 0,
+            base: Stats::new(),
+            growth: Stats::new(),
+            equipment: Equipment::new(),
+            modifiers: Vec::new(),
+            statuses: StatusTracker::new(),
+            bonuses: Stats::new(),
+        }
+    }
+
+    // Getters
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn get_hp(&self) -> i32 {
+        self.hp
+    }
+
+    pub fn get_pp(&self) -> i32 {
+        self.pp
+    }
+
+    /// A character with no HP left has fainted and cannot act
+    pub fn is_conscious(&self) -> bool {
+        self.hp > 0
+    }
+
+    /// Characters who have fainted, or are asleep or paralyzed, lose their
+    /// turn
+    pub fn can_act(&self) -> bool {
+        self.is_conscious() && self.statuses.get_incapacitating().is_none()
+    }
+
+    pub fn get_statuses(&self) -> &StatusTracker {
+        &self.statuses
+    }
+
+    pub fn has_status(&self, status: Status) -> bool {
+        self.statuses.has(status)
+    }
+
+    pub fn get_base(&self) -> &Stats {
+        &self.base
+    }
+
+    pub fn get_growth(&self) -> &Stats {
+        &self.growth
+    }
+
+    pub fn get_equipment(&self) -> &Equipment {
+        &self.equipment
+    }
+
+    pub fn get_modifiers(&self) -> &[Modifier] {
+        &self.modifiers
+    }
+
+    /// Returns the stats for the character's level, without equipment or
+    /// modifiers
+    pub fn get_natural_stats(&self) -> Stats {
+        self.base + self.growth.times(self.level as i32 - 1)
+    }
+
+    /// Returns a stat with the level, equipment and modifiers all counted
+    pub fn get_stat(&self, stat: Stat) -> i32 {
+        self.get_stats().get(stat)
+    }
+
+    /// Returns every stat with the level, equipment and modifiers all counted
+    pub fn get_stats(&self) -> Stats {
+        let mut stats = self.get_natural_stats() + self.bonuses;
+        for modifier in &self.modifiers {
+            stats.raise(modifier.get_stat(), modifier.get_amount());
+        }
+        stats
+    }
+
+    /// Checks that everything equipped exists in the registry
+    pub fn check(&self, items: &ItemRegistry) -> Result<(), String> {
+        match self
+            .equipment
+            .get_equipped()
+            .find(|&(_, id)| !items.contains(id.as_str()))
+        {
+            Some((_, id)) => Err(format!(
+                "{} has no item {:?} to equip",
+                self.name,
+                id.as_str()
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Character {
+    pub fn level(&mut self, level: u32) {
+        self.level = level.max(1);
+    }
+
+    pub fn base(&mut self, base: Stats) {
+        self.base = base;
+    }
+
+    pub fn growth(&mut self, growth: Stats) {
+        self.growth = growth;
+    }
+
+    /// Recovers all HP and PP
+    pub fn restore(&mut self) {
+        self.hp = self.get_stat(Stat::Hp);
+        self.pp = self.get_stat(Stat::Pp);
+    }
+
+    /// Recovers HP, up to the Hp stat. Returns how much was recovered
+    pub fn heal(&mut self, amount: i32) -> i32 {
+        let healed = (self.get_stat(Stat::Hp) - self.hp)
+            .max(0)
+            .min(amount.max(0));
+        self.hp += healed;
+        healed
+    }
+
+    /// Loses HP, down to 0. Returns how much was lost
+    pub fn hurt(&mut self, amount: i32) -> i32 {
+        let hurt = self.hp.min(amount.max(0));
+        self.hp -= hurt;
+        hurt
+    }
+
+    /// Goes up a level, growing every stat
+    pub fn level_up(&mut self) {
+        self.level += 1;
+    }
+
+    /// Raises a stat, or lowers it if amount is negative, for a while. With
+    /// no duration it lasts until the modifiers are cleared
+    pub fn modify(&mut self, stat: Stat, amount: i32, duration: Option<Duration>) {
+        self.modifiers.push(Modifier::new(stat, amount, duration));
+        self.clamp();
+    }
+
+    /// Gives the character a status for a while, or until it is cured.
+    /// Returns whether they did not have it already
+    pub fn inflict(&mut self, status: Status, duration: Option<Duration>) -> bool {
+        self.statuses.inflict(status, duration)
+    }
+
+    /// Takes a status away. Returns whether the character had it
+    pub fn cure(&mut self, status: Status) -> bool {
+        self.statuses.cure(status)
+    }
+
+    /// Ends a turn of battle. Poison hurts, then modifiers and statuses
+    /// count down
+    pub fn tick_turn(&mut self) -> Elapsed {
+        let poison = if self.has_status(Status::Poison) {
+            let damage = (self.get_stat(Stat::Hp) / POISON_FRACTION).max(1);
+            self.hurt(damage)
+        } else {
+            0
+        };
+
+        let mut worn_off = Vec::new();
+        self.modifiers.retain_mut(|modifier| {
+            let over = modifier.tick_turn();
+            if over {
+                worn_off.push(*modifier);
+            }
+            !over
+        });
+
+        self.clamp();
+        Elapsed {
+            poison,
+            worn_off,
+            recovered: self.statuses.tick_turn(),
+        }
+    }
+
+    /// Counts dt seconds down on modifiers and statuses that last for a time
+    pub fn tick_seconds(&mut self, dt: f64) -> Elapsed {
+        let mut worn_off = Vec::new();
+        self.modifiers.retain_mut(|modifier| {
+            let over = modifier.tick_seconds(dt);
+            if over {
+                worn_off.push(*modifier);
+            }
+            !over
+        });
+
+        self.clamp();
+        Elapsed {
+            poison: 0,
+            worn_off,
+            recovered: self.statuses.tick_seconds(dt),
+        }
+    }
+
+    /// Drops every modifier, ie when a battle ends
+    pub fn clear_modifiers(&mut self) {
+        self.modifiers.clear();
+        self.clamp();
+    }
+
+    /// Works the equipment bonuses out again, ie after the registry changes
+    pub fn recompute(&mut self, items: &ItemRegistry) {
+        self.bonuses = self.equipment.get_bonuses(items);
+        self.clamp();
+    }
+
+    /// Keeps HP and PP from going over the Hp and Pp stats after they drop
+    fn clamp(&mut self) {
+        self.hp = self.hp.min(self.get_stat(Stat::Hp)).max(0);
+        self.pp = self.pp.min(self.get_stat(Stat::Pp)).max(0);
+    }
+
+    /// Takes an item out of the inventory and puts it in its slot. Whatever
+    /// was in the slot goes back into the inventory and is returned. Nothing
+    /// changes if there is an error
+    pub fn equip(
+        &mut self,
+        inventory: &mut Inventory,
+        items: &ItemRegistry,
+        id: &str,
+    ) -> Result<Option<ItemId>, EquipError> {
+        let item = match items.get(id) {
+            Some(item) => item,
+            None => return Err(EquipError::UnknownItem(ItemId::new(id))),
+        };
+        let slot = match item.get_slot() {
+            Some(slot) => slot,
+            None => return Err(EquipError::NotEquippable(ItemId::new(id))),
+        };
+        if !item.can_equip(&self.name) {
+            return Err(EquipError::Restricted {
+                id: ItemId::new(id),
+                character: self.name.clone(),
+            });
+        }
+
+        inventory.rm_item(1, id)?;
+
+        // taking the new item out may have freed the slot the old one needs
+        if let Some(worn) = self
+            .equipment
+            .get(slot)
+            .and_then(|worn| items.get(worn.as_str()))
+        {
+            if let Err(error) = inventory.add_item(1, worn) {
+                inventory
+                    .add_item(1, item)
+                    .expect("there is room for the item just taken out");
+                return Err(error.into());
+            }
+        }
+
+        let previous = self.equipment.replace(slot, Some(ItemId::new(id)));
+        self.recompute(items);
+        Ok(previous)
+    }
+
+    /// Takes the item out of a slot and puts it back in the inventory
+    pub fn unequip(
+        &mut self,
+        inventory: &mut Inventory,
+        items: &ItemRegistry,
+        slot: EquipSlot,
+    ) -> Result<ItemId, EquipError> {
+        let id = match self.equipment.get(slot) {
+            Some(id) => id.clone(),
+            None => return Err(EquipError::Empty(slot)),
+        };
+
+        match items.get(id.as_str()) {
+            Some(item) => {
+                inventory.add_item(1, item)?;
+            }
+            None => return Err(EquipError::UnknownItem(id)),
+        }
+
+        self.equipment.replace(slot, None);
+        self.recompute(items);
+        Ok(id)
+    }
+}
+
+/// What happened to a character as a turn or some time passed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Elapsed {
+    // HP lost to poison
+    poison: i32,
+    worn_off: Vec<Modifier>,
+    recovered: Vec<Status>,
+}
+
+impl Elapsed {
+    // Getters
+    pub fn get_poison(&self) -> i32 {
+        self.poison
+    }
+
+    pub fn get_worn_off(&self) -> &[Modifier] {
+        &self.worn_off
+    }
+
+    pub fn get_recovered(&self) -> &[Status] {
+        &self.recovered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use game::tests::{inventory, items};
+    use inventory::InventoryError;
+    use items::{Effect, Item, ItemType};
+
+    fn ness() -> Character {
+        let mut base = Stats::new();
+        base.set(Stat::Offense, 2);
+        base.set(Stat::Defense, 2);
+
+        let mut ness = Character::new("Ness");
+        ness.base(base);
+        ness
+    }
+
+    #[test]
+    fn equipping_moves_items_out_of_the_inventory() {
+        let items = items();
+        let mut inventory = inventory(&items, &["cracked_bat", "baseball_bat", "baseball_cap"]);
+
+        let mut ness = ness();
+        assert_eq!(ness.equip(&mut inventory, &items, "cracked_bat"), Ok(None));
+        assert_eq!(ness.equip(&mut inventory, &items, "baseball_cap"), Ok(None));
+        assert_eq!(ness.get_stat(Stat::Offense), 6);
+        assert_eq!(ness.get_stat(Stat::Defense), 7);
+        assert!(inventory.has_item("cracked_bat").is_none());
+
+        // the better bat goes in and the old one back in the bag
+        assert_eq!(
+            ness.equip(&mut inventory, &items, "baseball_bat"),
+            Ok(Some(ItemId::new("cracked_bat")))
+        );
+        assert_eq!(ness.get_stat(Stat::Offense), 14);
+        assert!(inventory.has_item("cracked_bat").is_some());
+        assert!(inventory.has_item("baseball_bat").is_none());
+
+        assert_eq!(
+            ness.unequip(&mut inventory, &items, EquipSlot::Other),
+            Ok(ItemId::new("baseball_cap"))
+        );
+        assert_eq!(ness.get_stat(Stat::Defense), 2);
+        assert_eq!(
+            ness.unequip(&mut inventory, &items, EquipSlot::Other),
+            Err(EquipError::Empty(EquipSlot::Other))
+        );
+
+        let mut stats = Stats::new();
+        stats.set(Stat::Offense, 14);
+        stats.set(Stat::Defense, 2);
+        assert_eq!(ness.get_stats(), stats);
+    }
+
+    #[test]
+    fn stats_grow_with_levels_and_modifiers() {
+        let mut ness = ness();
+        let mut growth = Stats::new();
+        growth.set(Stat::Hp, 6);
+        growth.set(Stat::Offense, 2);
+        ness.growth(growth);
+
+        ness.level(3);
+        ness.level_up();
+        assert_eq!(ness.get_level(), 4);
+        assert_eq!(ness.get_stat(Stat::Hp), 18);
+        assert_eq!(ness.get_stat(Stat::Offense), 8);
+
+        ness.modify(Stat::Offense, 4, Some(Duration::Turns(1)));
+        ness.modify(Stat::Defense, -1, Some(Duration::Seconds(10.0)));
+        assert_eq!(ness.get_stat(Stat::Offense), 12);
+        assert_eq!(ness.get_stat(Stat::Defense), 1);
+
+        let elapsed = ness.tick_turn();
+        assert_eq!(
+            elapsed.get_worn_off(),
+            &[Modifier::new(Stat::Offense, 4, Some(Duration::Turns(0)))]
+        );
+        assert_eq!(ness.get_stat(Stat::Offense), 8);
+        assert_eq!(ness.get_modifiers().len(), 1);
+        assert_eq!(ness.tick_seconds(10.0).get_worn_off().len(), 1);
+
+        ness.modify(Stat::Defense, -1, None);
+
+        ness.clear_modifiers();
+        assert_eq!(ness.get_stat(Stat::Defense), 2);
+        assert_eq!(ness.get_natural_stats(), ness.get_stats());
+    }
+
+    #[test]
+    fn statuses_tick_with_the_turns() {
+        let mut ness = ness();
+        let mut base = *ness.get_base();
+        base.set(Stat::Hp, 30);
+        ness.base(base);
+        ness.restore();
+
+        assert!(ness.inflict(Status::Poison, None));
+        assert!(ness.inflict(Status::Sleep, Some(Duration::Turns(1))));
+        assert!(!ness.can_act());
+
+        let elapsed = ness.tick_turn();
+        assert_eq!(elapsed.get_poison(), 3);
+        assert_eq!(elapsed.get_recovered(), &[Status::Sleep]);
+        assert_eq!(ness.get_hp(), 27);
+        assert!(ness.can_act());
+
+        // poison keeps going until it is cured
+        assert_eq!(ness.tick_turn().get_poison(), 3);
+        assert!(ness.cure(Status::Poison));
+        assert_eq!(ness.tick_turn(), Elapsed::default());
+        assert_eq!(ness.get_hp(), 24);
+    }
+
+    #[test]
+    fn hp_stays_between_0_and_the_hp_stat() {
+        let mut ness = ness();
+        let mut base = *ness.get_base();
+        base.set(Stat::Hp, 30);
+        ness.base(base);
+        assert!(!ness.is_conscious());
+
+        ness.restore();
+        assert_eq!(ness.get_hp(), 30);
+        assert_eq!(ness.hurt(12), 12);
+        assert_eq!(ness.heal(20), 12);
+        assert_eq!(ness.get_hp(), 30);
+
+        assert_eq!(ness.hurt(50), 30);
+        assert!(!ness.is_conscious());
+        assert_eq!(ness.heal(-5), 0);
+    }
+
+    #[test]
+    fn hp_and_pp_drop_with_their_stats() {
+        let mut items = items();
+        items
+            .insert(Item::new(
+                ItemId::new("life_ribbon"),
+                String::from("Life Ribbon"),
+                String::from("Makes you feel sturdier."),
+                vec![Effect::Buff(10, Stat::Hp).into()],
+                ItemType::Armor,
+                1,
+            ))
+            .unwrap();
+        let mut inventory = inventory(&items, &["life_ribbon"]);
+
+        let mut ness = ness();
+        let mut base = *ness.get_base();
+        base.set(Stat::Hp, 30);
+        base.set(Stat::Pp, 10);
+        ness.base(base);
+        ness.equip(&mut inventory, &items, "life_ribbon").unwrap();
+        ness.modify(Stat::Pp, 5, Some(Duration::Seconds(1.0)));
+        ness.modify(Stat::Hp, 5, Some(Duration::Turns(1)));
+        ness.restore();
+        assert_eq!((ness.get_hp(), ness.get_pp()), (45, 15));
+
+        ness.tick_turn();
+        assert_eq!(ness.get_hp(), 40);
+        ness.tick_seconds(1.0);
+        assert_eq!(ness.get_pp(), 10);
+
+        ness.unequip(&mut inventory, &items, EquipSlot::Body)
+            .unwrap();
+        assert_eq!(ness.get_hp(), 30);
+
+        // lower stats take what is left with them
+        ness.modify(Stat::Pp, -4, None);
+        assert_eq!(ness.get_pp(), 6);
+        ness.clear_modifiers();
+        assert_eq!(ness.get_pp(), 6);
+    }
+
+    #[test]
+    fn some_things_cannot_be_equipped() {
+        let items = items();
+        let mut inventory = inventory(&items, &["cookie", "baseball_bat"]);
+
+        let mut paula = Character::new("Paula");
+        assert_eq!(
+            paula.equip(&mut inventory, &items, "baseball_bat"),
+            Err(EquipError::Restricted {
+                id: ItemId::new("baseball_bat"),
+                character: "Paula".to_string(),
+            })
+        );
+        assert_eq!(
+            paula.equip(&mut inventory, &items, "cookie"),
+            Err(EquipError::NotEquippable(ItemId::new("cookie")))
+        );
+        assert_eq!(
+            paula.equip(&mut inventory, &items, "baseball_cap"),
+            Err(EquipError::Inventory(InventoryError::NotFound(
+                ItemId::new("baseball_cap")
+            )))
+        );
+        assert!(paula.get_equipment().is_empty());
+        assert_eq!(inventory.get_used(), 2);
+    }
+
+    #[test]
+    fn nothing_changes_without_room_to_swap() {
+        let items = items();
+        let mut inventory = Inventory::with_capacity(1);
+        let mut ness = ness();
+
+        inventory
+            .add_item(1, items.get("cracked_bat").unwrap())
+            .unwrap();
+        ness.equip(&mut inventory, &items, "cracked_bat").unwrap();
+
+        // the bag is full, so the bat has nowhere to go
+        inventory.add_item(3, items.get("cookie").unwrap()).unwrap();
+        assert!(ness
+            .unequip(&mut inventory, &items, EquipSlot::Weapon)
+            .is_err());
+        assert_eq!(ness.get_stat(Stat::Offense), 6);
+        assert_eq!(
+            ness.get_equipment().get(EquipSlot::Weapon),
+            Some(&ItemId::new("cracked_bat"))
+        );
+    }
+}
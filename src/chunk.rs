@@ -0,0 +1,224 @@
+//! Splits the tiles of a layer into fixed size blocks, so only the blocks
+//! the camera can see have to be looked at when drawing
+
+use std::cell::{Ref, RefCell};
+
+use collision::Rect;
+use map::Map;
+use render::ChunkMesh;
+
+// how many tiles wide and high a chunk is
+pub const CHUNK_SIZE: usize = 16;
+
+/// A block of tiles from a layer. Chunks on the right and bottom edges of
+/// the map are smaller when the map does not divide evenly
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    // position of the top left tile of the chunk on the map, in tiles
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+
+    // rows of global tile ids one after the other, 0 means there is no tile
+    tiles: Vec<u32>,
+
+    // goes up every time a tile changes so the mesh knows it is stale
+    revision: u64,
+
+    // vertices built from the tiles, made the first time the chunk is drawn
+    mesh: RefCell<Option<ChunkMesh>>,
+}
+
+impl Chunk {
+    /// Creates a chunk from its tiles, laid out row after row
+    pub fn new(x: usize, y: usize, width: usize, height: usize, tiles: Vec<u32>) -> Chunk {
+        assert_eq!(
+            tiles.len(),
+            width * height,
+            "chunk has the wrong number of tiles"
+        );
+
+        Chunk {
+            x,
+            y,
+            width,
+            height,
+            tiles,
+            revision: 0,
+            mesh: RefCell::new(None),
+        }
+    }
+
+    // Getters
+    pub fn get_x(&self) -> usize {
+        self.x
+    }
+
+    pub fn get_y(&self) -> usize {
+        self.y
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Returns the tile at a position on the map, or 0 if the position is
+    /// outside of the chunk
+    pub fn get_tile(&self, x: usize, y: usize) -> u32 {
+        if !self.contains(x, y) {
+            return 0;
+        }
+
+        self.tiles[(y - self.y) * self.width + (x - self.x)]
+    }
+
+    /// Checks if a position on the map, in tiles, is part of the chunk
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        self.x <= x && x < self.x + self.width && self.y <= y && y < self.y + self.height
+    }
+
+    /// The area the chunk covers on the map in pixels
+    pub fn get_bounds(&self, tile_width: i32, tile_height: i32) -> Rect {
+        Rect::new(
+            (self.x as i32 * tile_width) as f64,
+            (self.y as i32 * tile_height) as f64,
+            (self.width as i32 * tile_width) as f64,
+            (self.height as i32 * tile_height) as f64,
+        )
+    }
+
+    /// Iterates over every tile as (x, y, gid), with x and y on the map
+    pub fn get_tiles(&self) -> impl Iterator<Item = (usize, usize, u32)> + '_ {
+        let (x, y, width) = (self.x, self.y, self.width);
+
+        self.tiles
+            .iter()
+            .enumerate()
+            .map(move |(i, &gid)| (x + i % width, y + i / width, gid))
+    }
+
+    /// Returns the vertices for the chunk, building them again if a tile has
+    /// changed or an animation has moved on since they were last built
+    pub fn get_mesh<T>(&self, map: &Map<T>) -> Ref<'_, ChunkMesh> {
+        let stale = match *self.mesh.borrow() {
+            Some(ref mesh) => mesh.is_stale(self, map),
+            None => true,
+        };
+
+        if stale {
+            *self.mesh.borrow_mut() = Some(ChunkMesh::build(self, map));
+        }
+
+        Ref::map(self.mesh.borrow(), |mesh| mesh.as_ref().unwrap())
+    }
+}
+
+impl Chunk {
+    /// Changes the tile at a position on the map. Returns false if the
+    /// position is outside of the chunk
+    pub fn set_tile(&mut self, x: usize, y: usize, gid: u32) -> bool {
+        if !self.contains(x, y) {
+            return false;
+        }
+
+        self.tiles[(y - self.y) * self.width + (x - self.x)] = gid;
+        self.revision += 1;
+
+        true
+    }
+}
+
+// two chunks are the same if they hold the same tiles, however their
+// meshes were built
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Chunk) -> bool {
+        self.x == other.x
+            && self.y == other.y
+            && self.width == other.width
+            && self.height == other.height
+            && self.tiles == other.tiles
+    }
+}
+
+/// Cuts rows of tiles into chunks of chunk_size, row by row
+pub fn split(rows: &[Vec<u32>], chunk_size: usize) -> Vec<Chunk> {
+    let height = rows.len();
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+
+    let mut chunks = vec![];
+    for y in (0..height).step_by(chunk_size) {
+        for x in (0..width).step_by(chunk_size) {
+            let (w, h) = (chunk_size.min(width - x), chunk_size.min(height - y));
+
+            // short rows are padded with empty tiles
+            let tiles = rows[y..y + h]
+                .iter()
+                .flat_map(|row| (x..x + w).map(move |i| row.get(i).cloned().unwrap_or(0)))
+                .collect();
+
+            chunks.push(Chunk::new(x, y, w, h, tiles));
+        }
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(width: u32, height: u32) -> Vec<Vec<u32>> {
+        (0..height)
+            .map(|y| (0..width).map(|x| y * width + x + 1).collect())
+            .collect()
+    }
+
+    #[test]
+    fn layers_split_into_chunks() {
+        let chunks = split(&rows(5, 3), 2);
+
+        // 3 chunks across and 2 down, the last column and row are cut short
+        assert_eq!(chunks.len(), 6);
+        assert_eq!(
+            (
+                chunks[2].get_x(),
+                chunks[2].get_width(),
+                chunks[2].get_height()
+            ),
+            (4, 1, 2)
+        );
+        assert_eq!(
+            (
+                chunks[3].get_y(),
+                chunks[3].get_width(),
+                chunks[3].get_height()
+            ),
+            (2, 2, 1)
+        );
+
+        assert_eq!(chunks[4].get_tile(3, 2), 14);
+        assert_eq!(chunks[4].get_tile(1, 1), 0);
+
+        let tiles: Vec<(usize, usize, u32)> = chunks[2].get_tiles().collect();
+        assert_eq!(tiles, vec![(4, 0, 5), (4, 1, 10)]);
+    }
+
+    #[test]
+    fn setting_tiles_bumps_the_revision() {
+        let mut chunk = split(&rows(2, 2), 2).remove(0);
+
+        assert!(chunk.set_tile(1, 1, 9));
+        assert!(!chunk.set_tile(2, 1, 9));
+        assert_eq!(chunk.get_tile(1, 1), 9);
+        assert_eq!(chunk.get_revision(), 1);
+    }
+}
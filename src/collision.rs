@@ -0,0 +1,349 @@
+//! Which tiles of a map can be walked on and how entities move through them
+
+use std::collections::HashSet;
+
+use map::{is_true, Layer, FLIP_FLAGS};
+
+/// An axis aligned box in pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Rect {
+    /// Creates a box with its top left corner at x, y
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // Getters
+    pub fn get_x(&self) -> f64 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> f64 {
+        self.y
+    }
+
+    pub fn get_width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> f64 {
+        self.height
+    }
+
+    /// Returns the same box moved by dx, dy
+    pub fn offset(&self, dx: f64, dy: f64) -> Rect {
+        Rect::new(self.x + dx, self.y + dy, self.width, self.height)
+    }
+
+    /// Checks if two boxes overlap. Touching edges do not count
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// Checks if a point is inside of the box
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        self.x <= x && x < self.x + self.width && self.y <= y && y < self.y + self.height
+    }
+}
+
+/// Determines if a tile placed on a layer blocks movement
+fn blocks(layer: &Layer, gid: u32, solid_gids: &HashSet<u32>) -> bool {
+    gid != 0 && (layer.is_collision() || solid_gids.contains(&(gid & !FLIP_FLAGS)))
+}
+
+/// A grid the size of the map that marks which tiles are solid
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollisionGrid {
+    width: i32,
+    height: i32,
+    tile_width: f64,
+    tile_height: f64,
+    // row major, true means the tile blocks movement
+    solid: Vec<bool>,
+}
+
+impl CollisionGrid {
+    /// Creates a grid where every tile can be walked on
+    pub fn new(width: i32, height: i32, tile_width: i32, tile_height: i32) -> CollisionGrid {
+        CollisionGrid {
+            width,
+            height,
+            tile_width: tile_width as f64,
+            tile_height: tile_height as f64,
+            solid: vec![false; (width.max(0) * height.max(0)) as usize],
+        }
+    }
+
+    /// Builds the grid for a map. Every tile placed on a collision layer is
+    /// solid, as is every tile on any layer whose gid is in solid_gids
+    pub fn from_layers(
+        layers: &[Layer],
+        solid_gids: &HashSet<u32>,
+        width: i32,
+        height: i32,
+        tile_width: i32,
+        tile_height: i32,
+    ) -> CollisionGrid {
+        let mut grid = CollisionGrid::new(width, height, tile_width, tile_height);
+
+        for layer in layers {
+            for (x, y, gid) in layer.iter_tiles() {
+                if blocks(layer, gid, solid_gids) {
+                    grid.set_solid(x as i32, y as i32, true);
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// Works out again if one tile is solid, ie after a layer was edited
+    pub fn update_tile(
+        &mut self,
+        layers: &[Layer],
+        solid_gids: &HashSet<u32>,
+        tile_x: usize,
+        tile_y: usize,
+    ) {
+        let solid = layers
+            .iter()
+            .any(|layer| blocks(layer, layer.get_tile(tile_x, tile_y), solid_gids));
+        self.set_solid(tile_x as i32, tile_y as i32, solid);
+    }
+
+    /// Finds the gids of every tile with a `solid` property set to true
+    pub fn solid_gids(tile_sets: &[tiled::Tileset]) -> HashSet<u32> {
+        tile_sets
+            .iter()
+            .flat_map(|tile_set| {
+                tile_set
+                    .tiles
+                    .iter()
+                    .filter(|tile| is_true(&tile.properties, "solid"))
+                    .map(move |tile| tile_set.first_gid + tile.id)
+            })
+            .collect()
+    }
+}
+
+impl CollisionGrid {
+    // Getters
+    pub fn get_width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> i32 {
+        self.height
+    }
+
+    /// Checks if a tile blocks movement. Everything outside of the map does
+    pub fn is_solid(&self, tile_x: i32, tile_y: i32) -> bool {
+        match self.index(tile_x, tile_y) {
+            Some(index) => self.solid[index],
+            None => true,
+        }
+    }
+
+    /// Marks a tile as solid or walkable, tiles outside of the map are ignored
+    pub fn set_solid(&mut self, tile_x: i32, tile_y: i32, solid: bool) {
+        if let Some(index) = self.index(tile_x, tile_y) {
+            self.solid[index] = solid;
+        }
+    }
+
+    /// Checks if any tile under the box is solid
+    pub fn collides(&self, rect: &Rect) -> bool {
+        let (first_x, last_x) = self.span(rect.x, rect.width, self.tile_width);
+        let (first_y, last_y) = self.span(rect.y, rect.height, self.tile_height);
+
+        (first_y..=last_y).any(|y| (first_x..=last_x).any(|x| self.is_solid(x, y)))
+    }
+
+    /// Moves a box by dx, dy and returns how far it actually got before
+    /// running into a solid tile. The x axis is resolved first so boxes slide
+    /// along walls instead of sticking to them
+    pub fn sweep(&self, rect: &Rect, dx: f64, dy: f64) -> (f64, f64) {
+        let dx = self.sweep_x(rect, dx);
+        let dy = self.sweep_y(&rect.offset(dx, 0.0), dy);
+        (dx, dy)
+    }
+
+    fn sweep_x(&self, rect: &Rect, dx: f64) -> f64 {
+        if dx == 0.0 {
+            return 0.0;
+        }
+
+        let (first_y, last_y) = self.span(rect.y, rect.height, self.tile_height);
+        let blocked = |x: i32| (first_y..=last_y).any(|y| self.is_solid(x, y));
+
+        if dx > 0.0 {
+            let edge = rect.x + rect.width;
+            let (first_x, last_x) = self.span(edge, dx, self.tile_width);
+            for x in first_x..=last_x {
+                let wall = x as f64 * self.tile_width;
+                if wall >= edge && blocked(x) {
+                    return wall - edge;
+                }
+            }
+        } else {
+            let (first_x, last_x) = self.span(rect.x + dx, -dx, self.tile_width);
+            for x in (first_x..=last_x).rev() {
+                let wall = (x + 1) as f64 * self.tile_width;
+                if wall <= rect.x && blocked(x) {
+                    return wall - rect.x;
+                }
+            }
+        }
+
+        dx
+    }
+
+    fn sweep_y(&self, rect: &Rect, dy: f64) -> f64 {
+        if dy == 0.0 {
+            return 0.0;
+        }
+
+        let (first_x, last_x) = self.span(rect.x, rect.width, self.tile_width);
+        let blocked = |y: i32| (first_x..=last_x).any(|x| self.is_solid(x, y));
+
+        if dy > 0.0 {
+            let edge = rect.y + rect.height;
+            let (first_y, last_y) = self.span(edge, dy, self.tile_height);
+            for y in first_y..=last_y {
+                let wall = y as f64 * self.tile_height;
+                if wall >= edge && blocked(y) {
+                    return wall - edge;
+                }
+            }
+        } else {
+            let (first_y, last_y) = self.span(rect.y + dy, -dy, self.tile_height);
+            for y in (first_y..=last_y).rev() {
+                let wall = (y + 1) as f64 * self.tile_height;
+                if wall <= rect.y && blocked(y) {
+                    return wall - rect.y;
+                }
+            }
+        }
+
+        dy
+    }
+
+    /// The first and last tile a run of pixels covers. A run ending exactly
+    /// on a tile edge does not cover the next tile
+    fn span(&self, start: f64, length: f64, tile: f64) -> (i32, i32) {
+        let first = (start / tile).floor() as i32;
+        let last = ((start + length) / tile).ceil() as i32 - 1;
+        (first, last.max(first))
+    }
+
+    fn index(&self, tile_x: i32, tile_y: i32) -> Option<usize> {
+        if tile_x < 0 || tile_y < 0 || tile_x >= self.width || tile_y >= self.height {
+            None
+        } else {
+            Some((tile_y * self.width + tile_x) as usize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLISION_MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.0" orientation="orthogonal" width="4" height="3" tilewidth="16" tileheight="16">
+ <tileset firstgid="1" name="rocks" tilewidth="16" tileheight="16">
+  <image source="rocks.png" width="32" height="16"/>
+  <tile id="1">
+   <properties>
+    <property name="solid" type="bool" value="true"/>
+   </properties>
+  </tile>
+ </tileset>
+ <layer name="Ground" width="4" height="3">
+  <data encoding="csv">
+1,1,1,1,
+1,2,1,1,
+1,1,1,1
+</data>
+ </layer>
+ <layer name="Collision" width="4" height="3" visible="0">
+  <data encoding="csv">
+0,0,0,1,
+0,0,0,1,
+0,0,0,0
+</data>
+ </layer>
+</map>"#;
+
+    fn grid() -> CollisionGrid {
+        let map = tiled::parse(COLLISION_MAP.as_bytes()).unwrap();
+        let layers: Vec<Layer> = map.layers.iter().map(Layer::from_tiled).collect();
+        let solid = CollisionGrid::solid_gids(&map.tilesets);
+
+        CollisionGrid::from_layers(&layers, &solid, 4, 3, 16, 16)
+    }
+
+    #[test]
+    fn solid_tiles_come_from_layer_and_properties() {
+        let grid = grid();
+
+        // the rock placed on the ground layer
+        assert!(grid.is_solid(1, 1));
+        // the collision layer
+        assert!(grid.is_solid(3, 0));
+        assert!(grid.is_solid(3, 1));
+
+        assert!(!grid.is_solid(0, 0));
+        assert!(!grid.is_solid(3, 2));
+
+        // off the edge of the map
+        assert!(grid.is_solid(-1, 0));
+        assert!(grid.is_solid(4, 2));
+    }
+
+    #[test]
+    fn sweep_stops_at_walls() {
+        let grid = grid();
+
+        // walking right along the top row runs into the collision layer
+        let rect = Rect::new(16.0, 0.0, 16.0, 16.0);
+        assert_eq!(grid.sweep(&rect, 40.0, 0.0), (16.0, 0.0));
+
+        // walking down into the rock
+        let rect = Rect::new(16.0, 0.0, 16.0, 8.0);
+        assert_eq!(grid.sweep(&rect, 0.0, 20.0), (0.0, 8.0));
+
+        // walking left stops at the edge of the map
+        let rect = Rect::new(8.0, 32.0, 16.0, 16.0);
+        assert_eq!(grid.sweep(&rect, -20.0, 0.0), (-8.0, 0.0));
+
+        // nothing in the way
+        let rect = Rect::new(0.0, 32.0, 16.0, 16.0);
+        assert_eq!(grid.sweep(&rect, 24.0, 0.0), (24.0, 0.0));
+    }
+
+    #[test]
+    fn sweep_slides_along_walls() {
+        let grid = grid();
+
+        // moving diagonally into the rock keeps the vertical movement
+        let rect = Rect::new(0.0, 16.0, 16.0, 16.0);
+        assert_eq!(grid.sweep(&rect, 8.0, 16.0), (0.0, 16.0));
+        assert!(!grid.collides(&rect.offset(0.0, 16.0)));
+        assert!(grid.collides(&rect.offset(8.0, 0.0)));
+    }
+}
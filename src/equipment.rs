@@ -0,0 +1,144 @@
+//! What each character has equipped. Weapons and armor are moved out of the
+//! inventory into a slot while they are worn, and their buffs count towards
+//! the character's stats
+
+use std::error::Error;
+use std::fmt;
+
+use inventory::InventoryError;
+use items::{Effect, ItemId, ItemRegistry};
+use stats::Stats;
+
+/// The places a character can wear something
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EquipSlot {
+    Weapon,
+    Body,
+    Arms,
+    Other,
+}
+
+/// Every slot, in the order they are listed in menus
+pub const EQUIP_SLOTS: [EquipSlot; 4] = [
+    EquipSlot::Weapon,
+    EquipSlot::Body,
+    EquipSlot::Arms,
+    EquipSlot::Other,
+];
+
+/// The item worn in each slot, if any
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Equipment {
+    weapon: Option<ItemId>,
+    body: Option<ItemId>,
+    arms: Option<ItemId>,
+    other: Option<ItemId>,
+}
+
+impl Equipment {
+    pub fn new() -> Equipment {
+        Equipment::default()
+    }
+
+    // Getters
+    /// Returns the item worn in a slot
+    pub fn get(&self, slot: EquipSlot) -> Option<&ItemId> {
+        match slot {
+            EquipSlot::Weapon => self.weapon.as_ref(),
+            EquipSlot::Body => self.body.as_ref(),
+            EquipSlot::Arms => self.arms.as_ref(),
+            EquipSlot::Other => self.other.as_ref(),
+        }
+    }
+
+    /// Returns the slots with something in them, along with what is there
+    pub fn get_equipped(&self) -> impl Iterator<Item = (EquipSlot, &ItemId)> {
+        EQUIP_SLOTS
+            .iter()
+            .filter_map(move |&slot| self.get(slot).map(|id| (slot, id)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.get_equipped().next().is_none()
+    }
+
+    /// Adds up the buffs and debuffs of everything worn. Items missing from
+    /// the registry count for nothing
+    pub fn get_bonuses(&self, items: &ItemRegistry) -> Stats {
+        let mut bonuses = Stats::new();
+
+        for (_, id) in self.get_equipped() {
+            let effects = items
+                .get(id.as_str())
+                .map_or(&[][..], |item| item.get_effects());
+            for effect in effects {
+                match *effect.get_effect() {
+                    Effect::Buff(amount, stat) => bonuses.raise(stat, amount),
+                    Effect::Debuff(amount, stat) => bonuses.raise(stat, -amount),
+                    _ => (),
+                }
+            }
+        }
+
+        bonuses
+    }
+}
+
+impl Equipment {
+    /// Puts an item in a slot, or empties it with None. Returns what was
+    /// there before
+    pub fn replace(&mut self, slot: EquipSlot, id: Option<ItemId>) -> Option<ItemId> {
+        let worn = match slot {
+            EquipSlot::Weapon => &mut self.weapon,
+            EquipSlot::Body => &mut self.body,
+            EquipSlot::Arms => &mut self.arms,
+            EquipSlot::Other => &mut self.other,
+        };
+
+        match id {
+            Some(id) => worn.replace(id),
+            None => worn.take(),
+        }
+    }
+}
+
+/// Why something could not be equipped or taken off
+#[derive(Debug, Clone, PartialEq)]
+pub enum EquipError {
+    // there is no party member with the name
+    NoCharacter(String),
+    // the item is not in the registry
+    UnknownItem(ItemId),
+    // the item is not a weapon or armor
+    NotEquippable(ItemId),
+    // the item can only be worn by certain characters
+    Restricted { id: ItemId, character: String },
+    // there is nothing in the slot to take off
+    Empty(EquipSlot),
+    // the item is not in the inventory, or there is no room to put it back
+    Inventory(InventoryError),
+}
+
+impl From<InventoryError> for EquipError {
+    fn from(error: InventoryError) -> EquipError {
+        EquipError::Inventory(error)
+    }
+}
+
+impl fmt::Display for EquipError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EquipError::NoCharacter(ref name) => write!(f, "there is no {} in the party", name),
+            EquipError::UnknownItem(ref id) => write!(f, "there is no item {}", id),
+            EquipError::NotEquippable(ref id) => write!(f, "{} cannot be equipped", id),
+            EquipError::Restricted {
+                ref id,
+                ref character,
+            } => write!(f, "{} cannot equip {}", character, id),
+            EquipError::Empty(slot) => write!(f, "nothing is equipped as {:?}", slot),
+            EquipError::Inventory(ref error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for EquipError {}
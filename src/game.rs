@@ -0,0 +1,402 @@
+//! The state of a game in progress, shared by every scene
+
+use std::collections::HashSet;
+
+use glium_graphics::Texture;
+use rand::{self, SeedableRng, XorShiftRng};
+
+use camera::Camera;
+use character::Character;
+use equipment::{EquipError, EquipSlot};
+use input::Input;
+use inventory::Inventory;
+use item_use::{self, ItemOutcome, UseError};
+use items::{ItemId, ItemRegistry};
+use player::{Direction, Player, SpriteSheet};
+use render::TextureLoader;
+use world::World;
+
+/// Everything a scene can look at or change. T is the texture type of the
+/// renderer the game is drawn with
+pub struct Game<T = Texture> {
+    world: World<T>,
+    loader: Box<dyn TextureLoader<Texture = T>>,
+    player: Player,
+    camera: Camera,
+    input: Input,
+    inventory: Inventory,
+    // every item that exists, which the inventory refers to by id
+    items: ItemRegistry,
+
+    // what the player looks like
+    sprites: T,
+    sheet: SpriteSheet,
+
+    // the characters travelling together, leader first
+    party: Vec<Character>,
+    // story flags, ie triggers that should only fire once
+    flags: HashSet<String>,
+    // seconds the game has been updated for, across every session
+    play_time: f64,
+    // rolls the chances of item effects
+    rng: XorShiftRng,
+}
+
+impl<T> Game<T> {
+    /// Creates a game. Nothing is on screen until a map is entered
+    pub fn new(
+        world: World<T>,
+        loader: Box<dyn TextureLoader<Texture = T>>,
+        sprites: T,
+        sheet: SpriteSheet,
+        camera: Camera,
+    ) -> Game<T> {
+        Game {
+            world,
+            loader,
+            player: Player::new(0, 0),
+            camera,
+            input: Input::default(),
+            inventory: Inventory::new(),
+            items: ItemRegistry::new(),
+            sprites,
+            sheet,
+            party: Vec::new(),
+            flags: HashSet::new(),
+            play_time: 0.0,
+            rng: rand::weak_rng(),
+        }
+    }
+
+    // Getters
+    pub fn get_world(&self) -> &World<T> {
+        &self.world
+    }
+
+    pub fn get_mut_world(&mut self) -> &mut World<T> {
+        &mut self.world
+    }
+
+    pub fn get_player(&self) -> &Player {
+        &self.player
+    }
+
+    pub fn get_mut_player(&mut self) -> &mut Player {
+        &mut self.player
+    }
+
+    pub fn get_camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn get_mut_camera(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Returns the world along with the camera, for drawing maps which needs
+    /// the camera's tile buffer
+    pub fn get_mut_view(&mut self) -> (&World<T>, &mut Camera) {
+        (&self.world, &mut self.camera)
+    }
+
+    pub fn get_input(&self) -> &Input {
+        &self.input
+    }
+
+    pub fn get_mut_input(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
+    pub fn get_inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    pub fn get_mut_inventory(&mut self) -> &mut Inventory {
+        &mut self.inventory
+    }
+
+    pub fn get_items(&self) -> &ItemRegistry {
+        &self.items
+    }
+
+    pub fn get_sprites(&self) -> &T {
+        &self.sprites
+    }
+
+    pub fn get_sheet(&self) -> &SpriteSheet {
+        &self.sheet
+    }
+
+    pub fn get_party(&self) -> &[Character] {
+        &self.party
+    }
+
+    /// Returns the party member with a name
+    pub fn get_character(&self, name: &str) -> Option<&Character> {
+        self.party
+            .iter()
+            .find(|character| character.get_name() == name)
+    }
+
+    pub fn get_flags(&self) -> &HashSet<String> {
+        &self.flags
+    }
+
+    pub fn get_play_time(&self) -> f64 {
+        self.play_time
+    }
+
+    pub fn get_mut_rng(&mut self) -> &mut XorShiftRng {
+        &mut self.rng
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.contains(flag)
+    }
+
+    /// Returns the size of a tile on the current map, or 16x16 before a map
+    /// has been entered
+    pub fn get_tile_size(&self) -> (i32, i32) {
+        match self.world.get_current() {
+            Some(map) => (map.get_tile_width(), map.get_tile_height()),
+            None => (16, 16),
+        }
+    }
+
+    /// Returns the middle of the player in pixels
+    pub fn get_player_center(&self) -> (f64, f64) {
+        let (tile_w, tile_h) = self.get_tile_size();
+        let (x, y) = self.player.get_position(tile_w, tile_h);
+        (x + tile_w as f64 / 2.0, y + tile_h as f64 / 2.0)
+    }
+}
+
+impl<T> Game<T> {
+    /// Sets a story flag. Returns false if it was already set
+    pub fn flag(&mut self, flag: &str) -> bool {
+        self.flags.insert(flag.to_string())
+    }
+
+    pub fn flags(&mut self, flags: HashSet<String>) {
+        self.flags = flags;
+    }
+
+    /// Sets who is in the party, working out their stats with the items
+    /// the game has
+    pub fn party(&mut self, party: Vec<Character>) {
+        self.party = party;
+        for character in &mut self.party {
+            character.recompute(&self.items);
+        }
+    }
+
+    pub fn inventory(&mut self, inventory: Inventory) {
+        self.inventory = inventory;
+    }
+
+    pub fn items(&mut self, items: ItemRegistry) {
+        self.items = items;
+        for character in &mut self.party {
+            character.recompute(&self.items);
+        }
+    }
+
+    pub fn play_time(&mut self, seconds: f64) {
+        self.play_time = seconds;
+    }
+
+    /// Seeds the random numbers so that chances come out the same every
+    /// time, ie for replays and tests. The seed must not be all zeros
+    pub fn seed(&mut self, seed: [u32; 4]) {
+        self.rng = XorShiftRng::from_seed(seed);
+    }
+
+    /// Has a party member equip an item from the inventory. Returns what
+    /// they had on in its place
+    pub fn equip(&mut self, name: &str, id: &str) -> Result<Option<ItemId>, EquipError> {
+        match self
+            .party
+            .iter_mut()
+            .find(|character| character.get_name() == name)
+        {
+            Some(character) => character.equip(&mut self.inventory, &self.items, id),
+            None => Err(EquipError::NoCharacter(name.to_string())),
+        }
+    }
+
+    /// Has a party member take off what is in a slot and put it in the
+    /// inventory
+    pub fn unequip(&mut self, name: &str, slot: EquipSlot) -> Result<ItemId, EquipError> {
+        match self
+            .party
+            .iter_mut()
+            .find(|character| character.get_name() == name)
+        {
+            Some(character) => character.unequip(&mut self.inventory, &self.items, slot),
+            None => Err(EquipError::NoCharacter(name.to_string())),
+        }
+    }
+
+    /// Has a party member use an item from the inventory on another party
+    /// member, or on themselves
+    pub fn use_item(
+        &mut self,
+        user: &str,
+        target: &str,
+        id: &str,
+    ) -> Result<ItemOutcome, UseError> {
+        item_use::use_item(
+            &mut self.party,
+            &mut self.inventory,
+            &self.items,
+            &mut self.rng,
+            user,
+            target,
+            id,
+        )
+    }
+
+    /// Counts dt seconds towards the time played, and off the party's timed
+    /// buffs and statuses
+    pub fn play(&mut self, dt: f64) {
+        self.play_time += dt;
+        for character in &mut self.party {
+            character.tick_seconds(dt);
+        }
+    }
+
+    /// Ends a battle turn, counting it off the party's buffs and statuses and
+    /// hurting anyone who is poisoned
+    pub fn end_turn(&mut self) {
+        for character in &mut self.party {
+            character.tick_turn();
+        }
+    }
+
+    /// Loads a map and puts the player on one of its spawns. An empty spawn
+    /// name picks the first spawn on the map
+    pub fn enter(&mut self, map: &str, spawn: &str) -> Result<(), String> {
+        let position = {
+            let map = self.world.enter(map, &mut *self.loader)?;
+            match map.get_objects().get_spawn(spawn) {
+                Some(spawn) => spawn.get_position(),
+                None => return Err(format!("no spawn named {:?}", spawn)),
+            }
+        };
+
+        self.place_player(position.0, position.1);
+        Ok(())
+    }
+
+    /// Loads a map and puts the player on a tile of it
+    pub fn enter_at(&mut self, map: &str, tile_x: i32, tile_y: i32) -> Result<(), String> {
+        self.world.enter(map, &mut *self.loader)?;
+
+        let (tile_w, tile_h) = self.get_tile_size();
+        self.place_player((tile_x * tile_w) as f64, (tile_y * tile_h) as f64);
+        Ok(())
+    }
+
+    /// Puts the player on the tile under a position in pixels and brings the
+    /// camera along
+    pub fn place_player(&mut self, x: f64, y: f64) {
+        let (tile_w, tile_h) = self.get_tile_size();
+        self.player.place(x as i32 / tile_w, y as i32 / tile_h);
+
+        if let Some(map) = self.world.get_current() {
+            self.camera
+                .bounds(map.get_pixel_width(), map.get_pixel_height());
+        }
+
+        let (x, y) = self.get_player_center();
+        self.camera.center_on(x, y);
+    }
+
+    /// Starts the player walking a step on the current map. Returns whether a
+    /// step was started
+    pub fn walk_player(&mut self, direction: Direction) -> bool {
+        match self.world.get_current() {
+            Some(map) => self.player.walk(direction, map.get_collision()),
+            None => false,
+        }
+    }
+
+    /// Takes the player through the warp they are standing on. Returns
+    /// whether there was one
+    pub fn travel(&mut self) -> Result<bool, String> {
+        let (x, y) = self.get_player_center();
+
+        match self.world.travel(x, y, &mut *self.loader) {
+            Some(result) => {
+                let (x, y) = result?;
+                self.place_player(x, y);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    use image::RgbaImage;
+    use render::SoftwareLoader;
+
+    /// A game in the fixture maps, standing at the front door of the town
+    pub fn game() -> Game<RgbaImage> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let sprites = SoftwareLoader
+            .load_texture_keyed(&root.join("assets/char_sample.png"), [0, 255, 0])
+            .unwrap();
+
+        let mut game = Game::new(
+            World::new(root.join("tests/fixtures/maps"), 4, usize::MAX),
+            Box::new(SoftwareLoader),
+            sprites,
+            SpriteSheet::char_sample(0),
+            Camera::load(0, 0, 160, 144),
+        );
+        game.items(items());
+        game.enter("town.tmx", "front_door").unwrap();
+        game
+    }
+
+    /// Every item in the assets
+    pub fn items() -> ItemRegistry {
+        ItemRegistry::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/items")).unwrap()
+    }
+
+    /// An inventory holding one of each item
+    pub fn inventory(items: &ItemRegistry, ids: &[&str]) -> Inventory {
+        let mut inventory = Inventory::new();
+        for id in ids {
+            inventory.add_item(1, items.get(id).unwrap()).unwrap();
+        }
+        inventory
+    }
+
+    #[test]
+    fn entering_places_the_player_at_the_spawn() {
+        let mut game = game();
+        assert_eq!(game.get_player().get_tile(), (4, 1));
+        assert_eq!(game.get_camera().get_bounds(), Some((128, 128)));
+
+        let error = game.enter("house.tmx", "back_door").unwrap_err();
+        assert!(error.contains("back_door"), "{}", error);
+    }
+
+    #[test]
+    fn warps_take_the_player_to_the_next_map() {
+        let mut game = game();
+        assert!(!game.travel().unwrap());
+
+        game.place_player(64.0, 0.0);
+        assert!(game.travel().unwrap());
+        assert_eq!(game.get_world().get_current_name(), Some("house.tmx"));
+        assert_eq!(game.get_player().get_tile(), (2, 3));
+    }
+}
@@ -0,0 +1,206 @@
+//! Runs the simulation at a fixed rate however fast frames are drawn, so
+//! walking speed and animations do not depend on the frame rate
+
+use piston::event_loop::EventSettings;
+use piston::input::{RenderEvent, UpdateEvent};
+
+/// Something that gets simulated in fixed steps
+pub trait Simulation {
+    /// Advances the simulation by one step of dt seconds
+    fn update(&mut self, dt: f64);
+}
+
+/// Keeps track of simulation time. It can be driven by piston's events in
+/// the game window, or by hand with step and advance where there is no
+/// window, ie in tests
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameLoop {
+    // updates per second
+    ups: u64,
+    // the most frames drawn per second
+    fps: u64,
+    // time that has passed but has not been simulated yet
+    accumulator: f64,
+    // frames longer than this are cut short so a stall (ie dragging the
+    // window) does not leave the game trying to catch up forever
+    max_frame_time: f64,
+    // how many updates have run
+    ticks: u64,
+}
+
+impl GameLoop {
+    /// Creates a loop that updates ups times a second and draws at most fps
+    /// frames a second
+    pub fn new(ups: u64, fps: u64) -> GameLoop {
+        GameLoop {
+            ups: ups.max(1),
+            fps,
+            accumulator: 0.0,
+            max_frame_time: 0.25,
+            ticks: 0,
+        }
+    }
+
+    // Getters
+    pub fn get_ups(&self) -> u64 {
+        self.ups
+    }
+
+    pub fn get_fps(&self) -> u64 {
+        self.fps
+    }
+
+    pub fn get_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Returns how long one update is in seconds
+    pub fn get_timestep(&self) -> f64 {
+        1.0 / self.ups as f64
+    }
+
+    /// Returns how far along the next update the current time is, from 0 to
+    /// 1. Drawing should blend the last two states of the game by this much
+    pub fn get_alpha(&self) -> f64 {
+        (self.accumulator / self.get_timestep()).clamp(0.0, 1.0)
+    }
+
+    /// The settings piston's event loop needs to send updates at this rate
+    pub fn get_event_settings(&self) -> EventSettings {
+        let mut settings = EventSettings::new();
+        settings.ups = self.ups;
+        settings.max_fps = self.fps;
+        settings
+    }
+}
+
+impl Default for GameLoop {
+    fn default() -> GameLoop {
+        GameLoop::new(60, 60)
+    }
+}
+
+impl GameLoop {
+    /// Sets the longest stretch of time one call to advance simulates
+    pub fn max_frame_time(&mut self, seconds: f64) {
+        self.max_frame_time = seconds;
+    }
+
+    /// Runs exactly one update
+    pub fn step<S: Simulation>(&mut self, simulation: &mut S) {
+        simulation.update(self.get_timestep());
+        self.ticks += 1;
+    }
+
+    /// Simulates elapsed seconds of real time, running as many whole updates
+    /// as fit and carrying the rest over to the next call. Returns how many
+    /// updates ran
+    pub fn advance<S: Simulation>(&mut self, simulation: &mut S, elapsed: f64) -> u32 {
+        self.accumulator += elapsed.clamp(0.0, self.max_frame_time);
+
+        let mut steps = 0;
+        while self.accumulator >= self.get_timestep() {
+            self.accumulator -= self.get_timestep();
+            self.step(simulation);
+            steps += 1;
+        }
+
+        steps
+    }
+
+    /// Handles an event from piston's event loop, which already sends update
+    /// events at a fixed rate. Update events step the simulation, render
+    /// events return the alpha to draw with
+    pub fn event<S, E>(&mut self, simulation: &mut S, event: &E) -> Option<f64>
+    where
+        S: Simulation,
+        E: UpdateEvent + RenderEvent,
+    {
+        if event.update_args().is_some() {
+            self.accumulator = 0.0;
+            self.step(simulation);
+        }
+
+        event.render_args().map(|args| {
+            self.accumulator = args.ext_dt;
+            self.get_alpha()
+        })
+    }
+}
+
+/// Blends between two values by alpha, for drawing between updates
+pub fn lerp(previous: f64, current: f64, alpha: f64) -> f64 {
+    previous + (current - previous) * alpha
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the time it has been given
+    #[derive(Default)]
+    struct Clock {
+        time: f64,
+        updates: u32,
+    }
+
+    impl Simulation for Clock {
+        fn update(&mut self, dt: f64) {
+            self.time += dt;
+            self.updates += 1;
+        }
+    }
+
+    #[test]
+    fn updates_run_at_a_fixed_rate() {
+        let mut game_loop = GameLoop::new(10, 60);
+        let clock = &mut Clock::default();
+
+        // a quarter of a second fits two updates with half of one left over
+        assert_eq!(game_loop.advance(clock, 0.25), 2);
+        assert!((game_loop.get_alpha() - 0.5).abs() < 1e-9);
+
+        assert_eq!(game_loop.advance(clock, 0.06), 1);
+        assert_eq!(clock.updates, 3);
+        assert!((clock.time - 0.3).abs() < 1e-9);
+        assert_eq!(game_loop.get_ticks(), 3);
+    }
+
+    #[test]
+    fn long_frames_are_cut_short() {
+        let mut game_loop = GameLoop::new(10, 60);
+        game_loop.max_frame_time(0.5);
+        let clock = &mut Clock::default();
+
+        assert_eq!(game_loop.advance(clock, 10.0), 5);
+    }
+
+    #[test]
+    fn stepping_is_deterministic() {
+        let mut game_loop = GameLoop::new(128, 60);
+        let first = &mut Clock::default();
+        let second = &mut Clock::default();
+
+        for _ in 0..128 {
+            game_loop.step(first);
+        }
+
+        // the same number of steps always gives the same result, however
+        // the time was split up
+        let mut game_loop = GameLoop::new(128, 60);
+        for _ in 0..32 {
+            game_loop.advance(second, 1.0 / 32.0);
+        }
+
+        assert_eq!(first.updates, 128);
+        assert_eq!(second.updates, 128);
+        assert_eq!(first.time, second.time);
+    }
+
+    #[test]
+    fn settings_match_the_rates() {
+        let settings = GameLoop::new(30, 144).get_event_settings();
+        assert_eq!((settings.ups, settings.max_fps), (30, 144));
+        assert_eq!(lerp(10.0, 20.0, 0.25), 12.5);
+    }
+}
@@ -0,0 +1,653 @@
+//! Turns raw buttons from the keyboard or a controller into the actions the
+//! game understands, so controls can be remapped without touching the scenes
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+use piston::input::{
+    Button, ButtonState, ControllerAxisArgs, ControllerButton, ControllerHat, HatState, Key,
+};
+use serde_json;
+
+use player::Direction;
+use replay::Recording;
+
+/// Something the player wants to do, whichever button they pressed for it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Confirm,
+    Cancel,
+    Menu,
+    Inventory,
+}
+
+impl Action {
+    /// The direction a movement action walks in
+    pub fn get_direction(self) -> Option<Direction> {
+        match self {
+            Action::MoveUp => Some(Direction::Up),
+            Action::MoveDown => Some(Direction::Down),
+            Action::MoveLeft => Some(Direction::Left),
+            Action::MoveRight => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+/// A controller stick axis. Pushing it past the deadzone one way or the other
+/// acts like holding a button
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    axis: u8,
+    negative: Action,
+    positive: Action,
+}
+
+impl AxisBinding {
+    pub fn new(axis: u8, negative: Action, positive: Action) -> AxisBinding {
+        AxisBinding {
+            axis,
+            negative,
+            positive,
+        }
+    }
+
+    // Getters
+    pub fn get_axis(&self) -> u8 {
+        self.axis
+    }
+
+    pub fn get_negative(&self) -> Action {
+        self.negative
+    }
+
+    pub fn get_positive(&self) -> Action {
+        self.positive
+    }
+}
+
+/// Which buttons trigger which actions. Controller buttons are matched on any
+/// controller, so they are stored with an id of 0
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bindings {
+    buttons: BTreeMap<Action, Vec<Button>>,
+    axes: Vec<AxisBinding>,
+    // how far a stick has to be pushed before it counts, from 0 to 1
+    deadzone: f64,
+}
+
+impl Bindings {
+    /// Creates bindings with nothing bound
+    pub fn new() -> Bindings {
+        Bindings {
+            buttons: BTreeMap::new(),
+            axes: Vec::new(),
+            deadzone: 0.5,
+        }
+    }
+
+    /// Reads bindings from a json file
+    pub fn load(path: &Path) -> Result<Bindings, String> {
+        let file = File::open(path).map_err(|error| format!("{:?}: {}", path, error))?;
+        serde_json::from_reader(file).map_err(|error| format!("{:?}: {}", path, error))
+    }
+
+    /// Writes the bindings to a json file, so they can be edited by hand
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|error| format!("{:?}: {}", path, error))?;
+        serde_json::to_writer_pretty(file, self).map_err(|error| format!("{:?}: {}", path, error))
+    }
+
+    // Getters
+    /// Returns the buttons bound to an action
+    pub fn get_buttons(&self, action: Action) -> &[Button] {
+        self.buttons
+            .get(&action)
+            .map_or(&[], |buttons| &buttons[..])
+    }
+
+    pub fn get_axes(&self) -> &[AxisBinding] {
+        &self.axes
+    }
+
+    pub fn get_deadzone(&self) -> f64 {
+        self.deadzone
+    }
+
+    /// Returns every action a button triggers. Diagonals on a hat trigger
+    /// the actions of both directions
+    pub fn get_actions(&self, button: Button) -> Vec<Action> {
+        let buttons: Vec<Button> = match normalize(button) {
+            Button::Hat(hat) => hat_directions(hat.state)
+                .iter()
+                .map(|&state| Button::Hat(ControllerHat { state, ..hat }))
+                .collect(),
+            button => vec![button],
+        };
+
+        self.buttons
+            .iter()
+            .filter(|&(_, bound)| bound.iter().any(|button| buttons.contains(button)))
+            .map(|(&action, _)| action)
+            .collect()
+    }
+}
+
+impl Bindings {
+    /// Adds a button to an action. A button can trigger more than one action
+    pub fn bind(&mut self, action: Action, button: Button) {
+        let button = normalize(button);
+        let buttons = self.buttons.entry(action).or_default();
+
+        if !buttons.contains(&button) {
+            buttons.push(button);
+        }
+    }
+
+    /// Takes a button off an action
+    pub fn unbind(&mut self, action: Action, button: Button) {
+        let button = normalize(button);
+
+        if let Some(buttons) = self.buttons.get_mut(&action) {
+            buttons.retain(|&bound| bound != button);
+        }
+    }
+
+    /// Makes a button the only one bound to an action
+    pub fn rebind(&mut self, action: Action, button: Button) {
+        self.buttons.remove(&action);
+        self.bind(action, button);
+    }
+
+    /// Binds both directions of a controller stick axis
+    pub fn bind_axis(&mut self, axis: u8, negative: Action, positive: Action) {
+        self.axes.retain(|binding| binding.axis != axis);
+        self.axes.push(AxisBinding::new(axis, negative, positive));
+    }
+
+    pub fn deadzone(&mut self, deadzone: f64) {
+        self.deadzone = deadzone;
+    }
+}
+
+impl Default for Bindings {
+    /// Arrow keys or WASD to walk, and the usual layout of a controller
+    fn default() -> Bindings {
+        let mut bindings = Bindings::new();
+
+        let keys = [
+            (Action::MoveUp, Key::Up, Key::W),
+            (Action::MoveDown, Key::Down, Key::S),
+            (Action::MoveLeft, Key::Left, Key::A),
+            (Action::MoveRight, Key::Right, Key::D),
+            (Action::Confirm, Key::Return, Key::Space),
+            (Action::Cancel, Key::Backspace, Key::X),
+            (Action::Menu, Key::Tab, Key::C),
+        ];
+        for &(action, first, second) in &keys {
+            bindings.bind(action, Button::Keyboard(first));
+            bindings.bind(action, Button::Keyboard(second));
+        }
+        bindings.bind(Action::Inventory, Button::Keyboard(Key::I));
+
+        let hat = [
+            (Action::MoveUp, HatState::Up),
+            (Action::MoveDown, HatState::Down),
+            (Action::MoveLeft, HatState::Left),
+            (Action::MoveRight, HatState::Right),
+        ];
+        for &(action, state) in &hat {
+            bindings.bind(action, Button::Hat(ControllerHat::new(0, 0, state)));
+        }
+
+        // A, B, Y and Start on most controllers
+        let controller = [
+            (Action::Confirm, 0),
+            (Action::Cancel, 1),
+            (Action::Inventory, 3),
+            (Action::Menu, 7),
+        ];
+        for &(action, button) in &controller {
+            bindings.bind(action, Button::Controller(ControllerButton::new(0, button)));
+        }
+
+        // the left stick
+        bindings.bind_axis(0, Action::MoveLeft, Action::MoveRight);
+        bindings.bind_axis(1, Action::MoveUp, Action::MoveDown);
+
+        bindings
+    }
+}
+
+/// Something that can hold actions down
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Source {
+    Button(Button),
+    // an axis pushed past the deadzone, true when it is pushed the positive way
+    Axis(u8, bool),
+    // an action pressed without a button, ie by a replay
+    Action(Action),
+}
+
+/// Which actions are held down, along with the ones that were pressed or
+/// released since the last update
+#[derive(Debug, Clone, PartialEq)]
+pub struct Input {
+    bindings: Bindings,
+    // what is being held down and the actions it holds, oldest first
+    held: Vec<(Source, Vec<Action>)>,
+    // actions pressed or released since the last update, in order
+    changes: Vec<(Action, ButtonState)>,
+    // how many updates have gone by
+    tick: u64,
+    // the tick recording started on, and what has been recorded since
+    recording: Option<(u64, Recording)>,
+}
+
+impl Input {
+    pub fn new(bindings: Bindings) -> Input {
+        Input {
+            bindings,
+            held: Vec::new(),
+            changes: Vec::new(),
+            tick: 0,
+            recording: None,
+        }
+    }
+
+    // Getters
+    pub fn get_bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    pub fn is_held(&self, action: Action) -> bool {
+        self.held
+            .iter()
+            .any(|(_, actions)| actions.contains(&action))
+    }
+
+    /// Checks if an action started being held since the last update
+    pub fn was_pressed(&self, action: Action) -> bool {
+        self.changes.contains(&(action, ButtonState::Press))
+    }
+
+    /// Checks if an action stopped being held since the last update
+    pub fn was_released(&self, action: Action) -> bool {
+        self.changes.contains(&(action, ButtonState::Release))
+    }
+
+    /// Returns the actions pressed or released since the last update
+    pub fn get_changes(&self) -> &[(Action, ButtonState)] {
+        &self.changes
+    }
+
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Returns the direction to walk in. When more than one is held the
+    /// latest one wins
+    pub fn get_direction(&self) -> Option<Direction> {
+        self.held
+            .iter()
+            .rev()
+            .flat_map(|(_, actions)| actions.iter())
+            .filter_map(|action| action.get_direction())
+            .next()
+    }
+}
+
+impl Default for Input {
+    fn default() -> Input {
+        Input::new(Bindings::default())
+    }
+}
+
+impl Input {
+    /// Swaps the bindings, letting go of everything held with the old ones
+    pub fn bindings(&mut self, bindings: Bindings) {
+        self.release_all();
+        self.bindings = bindings;
+    }
+
+    /// Handles a button being pressed or released. Returns the actions that
+    /// were not already held and now are
+    pub fn button(&mut self, button: Button, state: ButtonState) -> Vec<Action> {
+        let button = normalize(button);
+
+        // a hat only points one way at a time, moving it lets go of the last way
+        if let Button::Hat(hat) = button {
+            let moved = self
+                .held
+                .iter()
+                .filter_map(|&(source, _)| match source {
+                    Source::Button(Button::Hat(held)) if held.which == hat.which => Some(source),
+                    _ => None,
+                })
+                .collect::<Vec<Source>>();
+            for source in moved {
+                self.release(source);
+            }
+
+            if hat.state == HatState::Centered {
+                return Vec::new();
+            }
+        }
+
+        match state {
+            ButtonState::Press => self.press(Source::Button(button)),
+            ButtonState::Release => {
+                self.release(Source::Button(button));
+                Vec::new()
+            }
+        }
+    }
+
+    /// Handles a controller stick moving. Returns the actions that were not
+    /// already held and now are
+    pub fn axis(&mut self, args: ControllerAxisArgs) -> Vec<Action> {
+        let deadzone = self.bindings.deadzone;
+        let bound = self
+            .bindings
+            .axes
+            .iter()
+            .any(|binding| binding.axis == args.axis);
+        if !bound {
+            return Vec::new();
+        }
+
+        let pushed = if args.position >= deadzone {
+            Some(true)
+        } else if args.position <= -deadzone {
+            Some(false)
+        } else {
+            None
+        };
+
+        for &positive in &[true, false] {
+            if pushed != Some(positive) {
+                self.release(Source::Axis(args.axis, positive));
+            }
+        }
+
+        match pushed {
+            Some(positive) => self.press(Source::Axis(args.axis, positive)),
+            None => Vec::new(),
+        }
+    }
+
+    /// Presses an action without going through the bindings. Returns the
+    /// action if it was not already held
+    pub fn press_action(&mut self, action: Action) -> Vec<Action> {
+        self.press(Source::Action(action))
+    }
+
+    /// Lets go of an action pressed with press_action
+    pub fn release_action(&mut self, action: Action) {
+        self.release(Source::Action(action));
+    }
+
+    /// Forgets what was pressed and released, to be called after every update
+    pub fn end_frame(&mut self) {
+        self.changes.clear();
+        self.tick += 1;
+    }
+
+    /// Starts recording every action pressed or released. Actions already
+    /// held count as pressed on the first tick
+    pub fn record(&mut self) {
+        let mut recording = Recording::new();
+        for action in self.get_held_actions() {
+            recording.push(0, action, ButtonState::Press);
+        }
+
+        self.recording = Some((self.tick, recording));
+    }
+
+    /// Stops recording and returns what was recorded, if anything was
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        let tick = self.tick;
+
+        self.recording.take().map(|(start, mut recording)| {
+            recording.length(tick - start);
+            recording
+        })
+    }
+
+    /// Lets go of everything, ie when the window loses focus
+    pub fn release_all(&mut self) {
+        let sources = self
+            .held
+            .iter()
+            .map(|&(source, _)| source)
+            .collect::<Vec<Source>>();
+
+        for source in sources {
+            self.release(source);
+        }
+    }
+
+    fn press(&mut self, source: Source) -> Vec<Action> {
+        if self.held.iter().any(|&(held, _)| held == source) {
+            return Vec::new();
+        }
+
+        let actions = match source {
+            Source::Button(button) => self.bindings.get_actions(button),
+            Source::Axis(axis, positive) => self
+                .bindings
+                .axes
+                .iter()
+                .filter(|binding| binding.axis == axis)
+                .map(|binding| {
+                    if positive {
+                        binding.positive
+                    } else {
+                        binding.negative
+                    }
+                })
+                .collect(),
+            Source::Action(action) => vec![action],
+        };
+
+        let pressed = actions
+            .iter()
+            .cloned()
+            .filter(|&action| !self.is_held(action))
+            .collect::<Vec<Action>>();
+
+        self.held.push((source, actions));
+        for &action in &pressed {
+            self.change(action, ButtonState::Press);
+        }
+        pressed
+    }
+
+    fn release(&mut self, source: Source) {
+        let index = match self.held.iter().position(|&(held, _)| held == source) {
+            Some(index) => index,
+            None => return,
+        };
+
+        let (_, actions) = self.held.remove(index);
+        for action in actions {
+            if !self.is_held(action) {
+                self.change(action, ButtonState::Release);
+            }
+        }
+    }
+
+    fn change(&mut self, action: Action, state: ButtonState) {
+        self.changes.push((action, state));
+
+        if let Some((start, ref mut recording)) = self.recording {
+            recording.push(self.tick - start, action, state);
+        }
+    }
+
+    /// Every action being held, once each, oldest first
+    fn get_held_actions(&self) -> Vec<Action> {
+        let mut held = Vec::new();
+        for action in self.held.iter().flat_map(|(_, actions)| actions.iter()) {
+            if !held.contains(action) {
+                held.push(*action);
+            }
+        }
+        held
+    }
+}
+
+/// Controller buttons are bound on every controller, so the id is dropped
+fn normalize(button: Button) -> Button {
+    match button {
+        Button::Controller(button) => Button::Controller(ControllerButton { id: 0, ..button }),
+        Button::Hat(hat) => Button::Hat(ControllerHat { id: 0, ..hat }),
+        button => button,
+    }
+}
+
+/// Splits a hat state into the one or two directions it points in
+fn hat_directions(state: HatState) -> Vec<HatState> {
+    match state {
+        HatState::Centered => vec![],
+        HatState::RightUp => vec![HatState::Right, HatState::Up],
+        HatState::RightDown => vec![HatState::Right, HatState::Down],
+        HatState::LeftUp => vec![HatState::Left, HatState::Up],
+        HatState::LeftDown => vec![HatState::Left, HatState::Down],
+        state => vec![state],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    fn key(key: Key) -> Button {
+        Button::Keyboard(key)
+    }
+
+    #[test]
+    fn buttons_are_tracked_per_update() {
+        let mut input = Input::default();
+
+        assert_eq!(
+            input.button(key(Key::W), ButtonState::Press),
+            vec![Action::MoveUp]
+        );
+        assert!(input.is_held(Action::MoveUp));
+        assert!(input.was_pressed(Action::MoveUp));
+
+        // the other key for the same action does not press it again
+        assert!(input.button(key(Key::Up), ButtonState::Press).is_empty());
+        input.end_frame();
+        assert!(!input.was_pressed(Action::MoveUp));
+
+        input.button(key(Key::W), ButtonState::Release);
+        assert!(input.is_held(Action::MoveUp));
+        input.button(key(Key::Up), ButtonState::Release);
+        assert!(!input.is_held(Action::MoveUp));
+        assert!(input.was_released(Action::MoveUp));
+    }
+
+    #[test]
+    fn the_latest_direction_wins() {
+        let mut input = Input::default();
+
+        input.button(key(Key::Up), ButtonState::Press);
+        input.button(key(Key::Left), ButtonState::Press);
+        assert_eq!(input.get_direction(), Some(Direction::Left));
+
+        input.button(key(Key::Left), ButtonState::Release);
+        assert_eq!(input.get_direction(), Some(Direction::Up));
+    }
+
+    #[test]
+    fn controllers_work_on_any_id() {
+        let mut input = Input::default();
+
+        let start = Button::Controller(ControllerButton::new(3, 7));
+        assert_eq!(input.button(start, ButtonState::Press), vec![Action::Menu]);
+
+        // a diagonal on the d-pad holds both directions until it is centred
+        let hat = |state| Button::Hat(ControllerHat::new(1, 0, state));
+        input.button(hat(HatState::LeftDown), ButtonState::Press);
+        assert!(input.is_held(Action::MoveLeft) && input.is_held(Action::MoveDown));
+        input.button(hat(HatState::Centered), ButtonState::Press);
+        assert!(!input.is_held(Action::MoveLeft) && !input.is_held(Action::MoveDown));
+
+        // sticks only count past the deadzone
+        assert!(input.axis(ControllerAxisArgs::new(0, 1, 0.2)).is_empty());
+        assert_eq!(
+            input.axis(ControllerAxisArgs::new(0, 1, -0.9)),
+            vec![Action::MoveUp]
+        );
+        input.axis(ControllerAxisArgs::new(0, 1, 0.9));
+        assert!(!input.is_held(Action::MoveUp));
+        assert_eq!(input.get_direction(), Some(Direction::Down));
+    }
+
+    #[test]
+    fn recording_counts_updates() {
+        let mut input = Input::default();
+        input.button(key(Key::Up), ButtonState::Press);
+        input.end_frame();
+
+        // what is already held is pressed at the start of the recording
+        input.record();
+        input.end_frame();
+        input.button(key(Key::Up), ButtonState::Release);
+        input.press_action(Action::Confirm);
+        input.end_frame();
+
+        let recording = input.stop_recording().unwrap();
+        let actions = recording
+            .get_actions()
+            .iter()
+            .map(|recorded| {
+                (
+                    recorded.get_tick(),
+                    recorded.get_action(),
+                    recorded.get_state(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![
+                (0, Action::MoveUp, ButtonState::Press),
+                (1, Action::MoveUp, ButtonState::Release),
+                (1, Action::Confirm, ButtonState::Press),
+            ]
+        );
+        assert_eq!(recording.get_length(), 2);
+        assert!(!input.is_recording());
+    }
+
+    #[test]
+    fn bindings_can_be_remapped_and_saved() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(Action::Confirm, key(Key::E));
+        assert_eq!(bindings.get_buttons(Action::Confirm), &[key(Key::E)]);
+        assert!(bindings.get_actions(key(Key::Return)).is_empty());
+
+        let path =
+            env::temp_dir().join(format!("uranusbound-bindings-{}.json", std::process::id()));
+        bindings.save(&path).unwrap();
+        let loaded = Bindings::load(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), bindings);
+
+        assert!(Bindings::load(Path::new("nowhere.json")).is_err());
+    }
+}
@@ -3,182 +3,139 @@
 //! This represents the inventory for interactable objects
 
 // For serializing inventory to json
-extern crate serde_json;
+use serde_json;
 
 // For the ability to sort items into the correct bag
-use items::{Effect, Item, ItemType};
+use items::{Item, ItemId, ItemRegistry, ItemType};
 
 // For Serialization/Deserialization
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
+use std::path::Path;
+
+/// How many slots an inventory has unless it is given a capacity. Each
+/// different item takes up one slot
+pub const DEFAULT_CAPACITY: usize = 14;
 
 /// Inventory struct
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Inventory {
     /// An objects item bag
     bags: Bag,
     /// An interger that determines the amount of money held
     wallet: i32,
+    /// The number of slots shared between the bags
+    capacity: usize,
 }
 
 // Related Functions
 impl Inventory {
-    /// Returns an empty Inventory
+    /// Returns an empty Inventory with the default number of slots
     pub fn new() -> Inventory {
+        Inventory::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Returns an empty Inventory with a number of slots
+    pub fn with_capacity(capacity: usize) -> Inventory {
         Inventory {
             bags: Bag::new(),
             wallet: 0,
+            capacity,
         }
     }
+}
 
-    #[test]
-    pub fn test_inventory() {
-        /// Tests the funcitonality of the inventory system
-        //******************************************************************************
-        // Start Inventory Test
-
-        println!("\n\nStarting the Inventory tests\n\n");
-
-        let potion = Item::new(
-            String::from("Potion"),
-            String::from("This item heals you"),
-            Effect::Heal(15),
-            ItemType::Consumable,
-            15,
-        );
-
-        let mana_pot = Item::new(
-            String::from("Mana Pot"),
-            String::from("This item restores your mana"),
-            Effect::Buff(15, String::from("Ap")),
-            ItemType::Consumable,
-            20,
-        );
-
-        let sword = Item::new(
-            String::from("Sword"),
-            String::from("A cheap iron sword"),
-            Effect::Damage(25),
-            ItemType::Weapon,
-            35,
-        );
+impl Default for Inventory {
+    fn default() -> Inventory {
+        Inventory::new()
+    }
+}
 
-        let sheild = Item::new(
-            String::from("Sheild"),
-            String::from("A cheap iron sheild"),
-            Effect::Buff(10, String::from("Def")),
-            ItemType::Armor,
-            25,
-        );
+// Methods
+impl Inventory {
+    /// Adds an item to the appropriate bag. Items with the same id share a
+    /// slot, even if the rest of them differ. Nothing is added unless all of
+    /// qty fits
+    pub fn add_item(&mut self, qty: i32, item: &Item) -> Result<Holding, InventoryError> {
+        if qty <= 0 {
+            return Err(InventoryError::InvalidQuantity(qty));
+        }
 
-        let true_or_false = |result: Option<(usize, i32)>| -> bool {
-            match result {
-                Some((_, _)) => true,
-                None => false,
+        let held = match self.has_item(item.get_id().as_str()) {
+            Some(holding) => holding.qty,
+            None if self.get_used() >= self.capacity => {
+                return Err(InventoryError::Full {
+                    id: item.get_id().clone(),
+                    space: 0,
+                })
             }
+            None => 0,
         };
 
-        let mut inv: Inventory = Inventory::new();
-
-        println!("Passed: Created items and inventory");
-
-        // add dumby items to the inventory
-        inv.add_item(5, &potion);
-        inv.add_item(15, &mana_pot);
-        inv.add_item(1, &sword);
-        inv.add_item(1, &sheild);
-        inv.add_money(100);
-
-        // verify that the search alg works and that the items are sucessfully
-        // sorted into the appropriate bag
-        assert!(true_or_false(inv.has_item(&potion)), true);
-        assert!(true_or_false(inv.has_item(&mana_pot)), true);
-        assert!(true_or_false(inv.has_item(&sword)), true);
-        assert!(true_or_false(inv.has_item(&sheild)), true);
-
-        println!("Passed: Items added to inventory");
+        if held + qty > item.get_max_stack() {
+            return Err(InventoryError::Full {
+                id: item.get_id().clone(),
+                space: (item.get_max_stack() - held).max(0),
+            });
+        }
 
-        // add duplicate items to verify that it updates the existing Entry instead
-        // of making a new one
-        inv.add_item(5, &potion);
-        //assert!(inv.bags.consumable_bag.len() == , true);
+        Ok(self.bags.add_item(qty, item))
+    }
 
-        println!("Passed: Entered duplicate item and the existing entry was updated");
+    /// Determines if a particular item is in the inventory, and where
+    pub fn has_item(&self, id: &str) -> Option<Holding> {
+        self.bags.find_item(id)
+    }
 
-        // tests to see if the it handles the removal of more money that is gon hand
-        inv.rm_money(600);
-        assert!(inv.get_wallet() == 0, true);
-        println!("Passed: Removed more money than was on hand and the wallet did not go negative");
-
-        // removes more than is currently in inventory to see if the entry is
-        // removed as intented, then adds them back and removes the exact qty
-        inv.rm_item(16, &mana_pot);
-        assert!(inv.bags.consumable_bag.len() == 1, true);
-        println!("Passed: Removed an entry from the bags with a qty > on hand");
-
-        inv.add_item(15, &mana_pot);
-        assert!(inv.bags.consumable_bag.len() == 2, true);
-        inv.rm_item(15, &mana_pot);
-        assert!(inv.bags.consumable_bag.len() == 1, true);
-        println!("Passed: Added then removed an entry of equal qty on hand from the bags");
-
-        inv.rm_item(5, &potion);
-        assert!(true_or_false(inv.has_item(&potion)), true);
-        println!("Passed: Removed a partial quantity from inventory and the entry persisted");
-
-        inv.save();
-        let inv2 = Inventory::load(String::from("inventory.json"));
-        let invstr = serde_json::to_string_pretty(&inv).unwrap();
-        let inv2str = serde_json::to_string_pretty(&inv2).unwrap();
+    /// Removes qty of an item from inventory. The entry is dropped once none
+    /// are left. Nothing is removed unless at least qty are held
+    pub fn rm_item(&mut self, qty: i32, id: &str) -> Result<Removal, InventoryError> {
+        if qty <= 0 {
+            return Err(InventoryError::InvalidQuantity(qty));
+        }
 
-        assert!(inv == inv2, true);
-        assert!(invstr == inv2str, true);
-        println!("Passed: Serialized Inventory to JSON, then back again");
+        let holding = match self.has_item(id) {
+            Some(holding) => holding,
+            None => return Err(InventoryError::NotFound(ItemId::new(id))),
+        };
+        if holding.qty < qty {
+            return Err(InventoryError::Insufficient {
+                id: ItemId::new(id),
+                held: holding.qty,
+                wanted: qty,
+            });
+        }
 
-        println!("\n\n** All tests passed for the Inventory!\n\n");
+        let bag = self.bags.get_mut_bag(&holding.item_type);
+        let remaining = bag[holding.index].rm_qty(qty);
+        let entry = if remaining == 0 {
+            bag.swap_remove(holding.index)
+        } else {
+            bag[holding.index].clone()
+        };
 
-        // end inventory test
-        //******************************************************************************
+        Ok(Removal {
+            id: entry.id,
+            removed: qty,
+            remaining,
+        })
     }
-}
 
-// Methods
-impl Inventory {
-    /// Adds an item to the appropriate bag
-    pub fn add_item(&mut self, qty: i32, item: &Item) {
-        self.bags.add_item(qty, item);
+    /// Returns how many slots the inventory has
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
     }
 
-    /// Determines if a particular item is in the inventory
-    pub fn has_item(&self, item: &Item) -> Option<(usize, i32)> {
-        match self.bags.find_item(item) {
-            Some((location, qty_held)) => Some((location, qty_held)),
-            None => None,
-        }
+    /// Returns how many slots have something in them
+    pub fn get_used(&self) -> usize {
+        self.get_entries().count()
     }
 
-    /// Removes an item from inventory
-    pub fn rm_item(&mut self, qty: i32, item: &Item) {
-        let rm = |loc: usize, qty: i32, bag: &mut Vec<Entry>| {
-            let remains = bag[loc].rm_qty(qty);
-
-            if remains <= 0 {
-                bag.swap_remove(loc);
-            }
-        };
-
-        if let Some((location, qty_held)) = self.has_item(item) {
-            match item.get_type() {
-                ItemType::Armor => {
-                    rm(location, qty, &mut self.bags.armor_bag);
-                }
-                ItemType::Consumable => {
-                    rm(location, qty, &mut self.bags.consumable_bag);
-                }
-                ItemType::Weapon => {
-                    rm(location, qty, &mut self.bags.weapon_bag);
-                }
-            }
-        }
+    /// Changes how many slots the inventory has. Items already held are kept
+    /// even if there are more of them than slots
+    pub fn capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
     }
 
     /// Adds money to the wallet
@@ -202,26 +159,47 @@ impl Inventory {
         self.wallet
     }
 
-    /// Serialize the inventory to a JSON file. This should be called when the player saves the game
-    pub fn save(&self) {
-        // Open the file for writing, fail if it doesn't open
-        let file = File::create("inventory.json").unwrap();
+    /// Returns every entry held, armor first, then consumables and weapons
+    pub fn get_entries(&self) -> impl Iterator<Item = &Entry> {
+        self.bags
+            .armor_bag
+            .iter()
+            .chain(self.bags.consumable_bag.iter())
+            .chain(self.bags.weapon_bag.iter())
+    }
+
+    /// Checks that every item held exists in the registry, ie after loading
+    /// a save written before an item was taken out of the game
+    pub fn check(&self, items: &ItemRegistry) -> Result<(), String> {
+        match self
+            .get_entries()
+            .find(|entry| !items.contains(entry.get_id().as_str()))
+        {
+            Some(entry) => Err(format!("there is no item {:?}", entry.get_id().as_str())),
+            None => Ok(()),
+        }
+    }
+
+    /// Serialize the inventory to a JSON file. Saving the whole game is done
+    /// with a SaveGame, which includes the inventory
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        // Open the file for writing
+        let file = File::create(path).map_err(|error| format!("{:?}: {}", path, error))?;
         // write the generated json to the file
-        serde_json::to_writer_pretty(file, self).unwrap();
+        serde_json::to_writer_pretty(file, self).map_err(|error| format!("{:?}: {}", path, error))
     }
 
-    /// Deserialize JSON into an Inventory Struct. This should be called when the player loads the game
-    pub fn load(file_name: String) -> Inventory {
+    /// Deserialize JSON into an Inventory Struct
+    pub fn load(path: &Path) -> Result<Inventory, String> {
         // open the file to read from
-        let file = File::open(file_name).unwrap();
+        let file = File::open(path).map_err(|error| format!("{:?}: {}", path, error))?;
         // deserialize the object from the file
-        let inv: Inventory = serde_json::from_reader(file).unwrap();
-        inv
+        serde_json::from_reader(file).map_err(|error| format!("{:?}: {}", path, error))
     }
 }
 
 /// A struct that contains three vectors for each item type: Armor, Consumable, Weapon
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct Bag {
     /// Vector that holds armor items
     armor_bag: Vec<Entry>,
@@ -245,94 +223,198 @@ impl Bag {
 
 // Methods
 impl Bag {
-    /// Defines how to add items together. Used to increase the quantity held
-    //fn add
-
     /// Public function that adds an item to the correct item vector
-    fn add_item(&mut self, qty: i32, item: &Item) {
-        if let Some((location, _qty_held)) = self.find_item(&item) {
-            match item.get_type() {
-                ItemType::Armor => {
-                    self.armor_bag[location].add_qty(qty);
-                }
-                ItemType::Consumable => {
-                    self.consumable_bag[location].add_qty(qty);
-                }
-                ItemType::Weapon => {
-                    self.weapon_bag[location].add_qty(qty);
-                }
-            }
+    fn add_item(&mut self, qty: i32, item: &Item) -> Holding {
+        if let Some(mut holding) = self.find_item(item.get_id().as_str()) {
+            holding.qty = self.get_mut_bag(&holding.item_type)[holding.index].add_qty(qty);
+            holding
         } else {
-            match item.get_type() {
-                ItemType::Armor => {
-                    self.armor_bag.push(Entry::new(qty, item.clone()));
-                }
-                ItemType::Consumable => {
-                    self.consumable_bag.push(Entry::new(qty, item.clone()));
-                }
-                ItemType::Weapon => {
-                    self.weapon_bag.push(Entry::new(qty, item.clone()));
-                }
+            let bag = self.get_mut_bag(&item.get_type());
+            bag.push(Entry::new(qty, item.get_id().clone()));
+            Holding {
+                item_type: item.get_type(),
+                index: bag.len() - 1,
+                qty,
             }
         }
     }
 
-    /// Locates an item location in the appropriate ItemType vector. Return None if it is not found
-    fn find_item(&self, item: &Item) -> Option<(usize, i32)> {
-        let search = |thing: &Item, bag: &Vec<Entry>| -> Option<(usize, i32)> {
-            if !bag.is_empty() {
-                for (pos, entry) in bag.iter().enumerate() {
-                    if entry.item == *thing {
-                        return Some((pos, entry.get_qty()));
-                    }
-                }
+    /// Locates an item by id in each of the vectors. Return None if it is not found
+    fn find_item(&self, id: &str) -> Option<Holding> {
+        let bags = [
+            (ItemType::Armor, &self.armor_bag),
+            (ItemType::Consumable, &self.consumable_bag),
+            (ItemType::Weapon, &self.weapon_bag),
+        ];
+
+        for (item_type, bag) in bags.iter() {
+            if let Some(index) = bag.iter().position(|entry| entry.id.as_str() == id) {
+                return Some(Holding {
+                    item_type: item_type.clone(),
+                    index,
+                    qty: bag[index].get_qty(),
+                });
             }
-            return None;
-        };
+        }
+        None
+    }
+
+    /// Returns the vector that holds an item type
+    fn get_mut_bag(&mut self, item_type: &ItemType) -> &mut Vec<Entry> {
+        match *item_type {
+            ItemType::Armor => &mut self.armor_bag,
+            ItemType::Consumable => &mut self.consumable_bag,
+            ItemType::Weapon => &mut self.weapon_bag,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Where an item is held in the inventory
+pub struct Holding {
+    /// The bag the item is in
+    item_type: ItemType,
+    /// The position of the item's entry in its bag
+    index: usize,
+    /// The amount of the item held
+    qty: i32,
+}
+
+// Methods
+impl Holding {
+    /// Returns the type of bag the item is in
+    pub fn get_type(&self) -> ItemType {
+        self.item_type.clone()
+    }
+
+    /// Returns the position of the entry in its bag
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the quantity held
+    pub fn get_qty(&self) -> i32 {
+        self.qty
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// What was taken out of the inventory by removing an item
+pub struct Removal {
+    /// The id of the item removed
+    id: ItemId,
+    /// How many were removed
+    removed: i32,
+    /// How many are left in the inventory
+    remaining: i32,
+}
+
+// Methods
+impl Removal {
+    /// Returns the id of the item removed
+    pub fn get_id(&self) -> &ItemId {
+        &self.id
+    }
+
+    /// Returns how many were removed
+    pub fn get_removed(&self) -> i32 {
+        self.removed
+    }
+
+    /// Returns how many are left
+    pub fn get_remaining(&self) -> i32 {
+        self.remaining
+    }
+
+    /// Determines if the last of the item was removed
+    pub fn is_gone(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Why an item could not be added to or removed from the inventory
+pub enum InventoryError {
+    /// There is no free slot for the item, or its slot is at its max stack.
+    /// Holds how many more of the item would have fit
+    Full {
+        /// The item that did not fit
+        id: ItemId,
+        /// How many of the item could still be added
+        space: i32,
+    },
+    /// The item is not in the inventory
+    NotFound(ItemId),
+    /// Fewer of the item are held than were asked for
+    Insufficient {
+        /// The item asked for
+        id: ItemId,
+        /// How many are held
+        held: i32,
+        /// How many were asked for
+        wanted: i32,
+    },
+    /// Items can only be added or removed one or more at a time
+    InvalidQuantity(i32),
+}
 
-        match item.get_type() {
-            ItemType::Armor => search(item, &self.armor_bag),
-            ItemType::Consumable => search(item, &self.consumable_bag),
-            ItemType::Weapon => search(item, &self.weapon_bag),
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InventoryError::Full { ref id, space } => {
+                write!(f, "there is only room for {} more {}", space, id)
+            }
+            InventoryError::NotFound(ref id) => write!(f, "there is no {} in the inventory", id),
+            InventoryError::Insufficient {
+                ref id,
+                held,
+                wanted,
+            } => write!(f, "wanted {} {} but only {} are held", wanted, id, held),
+            InventoryError::InvalidQuantity(qty) => write!(f, "{} is not a quantity of items", qty),
         }
     }
 }
 
+impl Error for InventoryError {}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-/// Represents an item in inventory
-struct Entry {
+/// Represents an item in inventory. Only the id of the item is kept, the
+/// rest of it is looked up in the ItemRegistry
+pub struct Entry {
     /// The amount of the item in the bag
     qty: i32,
-    /// The item held in the bag
-    item: Item,
+    /// The id of the item held in the bag
+    id: ItemId,
 }
 
 // Related functions
 impl Entry {
     /// Creates a new Entry struct
-    fn new(qty: i32, item: Item) -> Entry {
-        Entry { qty, item }
+    fn new(qty: i32, id: ItemId) -> Entry {
+        Entry { qty, id }
     }
 }
 // Methods
 impl Entry {
-    /// Returns the name of the item in the Entry
-    fn get_name(&self) -> String {
-        self.item.get_name()
+    /// Returns the id of the item in the Entry
+    pub fn get_id(&self) -> &ItemId {
+        &self.id
     }
 
-    fn get_item(&self) -> &Item {
-        &self.item
+    /// Returns the item held in the Entry
+    pub fn get_item<'a>(&self, items: &'a ItemRegistry) -> Option<&'a Item> {
+        items.get(self.id.as_str())
     }
 
     /// Returns the quantity held in the Entry
-    fn get_qty(&self) -> i32 {
+    pub fn get_qty(&self) -> i32 {
         self.qty
     }
 
     /// Adds to the quantity held in the Entry
-    fn add_qty(&mut self, qty_to_add: i32) {
+    fn add_qty(&mut self, qty_to_add: i32) -> i32 {
         self.qty += qty_to_add;
+        self.qty
     }
 
     /// Reduces the quantity held in the Entry
@@ -341,3 +423,257 @@ impl Entry {
         self.qty
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use items::Effect;
+    use stats::Stat;
+    use tempfile::TempDir;
+
+    /// Tests the funcitonality of the inventory system
+    #[test]
+    fn test_inventory() {
+        //******************************************************************************
+        // Start Inventory Test
+
+        let potion = Item::new(
+            ItemId::new("potion"),
+            String::from("Potion"),
+            String::from("This item heals you"),
+            vec![Effect::Heal(15).into()],
+            ItemType::Consumable,
+            15,
+        );
+
+        let mana_pot = Item::new(
+            ItemId::new("mana_pot"),
+            String::from("Mana Pot"),
+            String::from("This item restores your mana"),
+            vec![Effect::Buff(15, Stat::Pp).into()],
+            ItemType::Consumable,
+            20,
+        );
+
+        let sword = Item::new(
+            ItemId::new("sword"),
+            String::from("Sword"),
+            String::from("A cheap iron sword"),
+            vec![Effect::Damage(25).into()],
+            ItemType::Weapon,
+            35,
+        );
+
+        let sheild = Item::new(
+            ItemId::new("sheild"),
+            String::from("Sheild"),
+            String::from("A cheap iron sheild"),
+            vec![Effect::Buff(10, Stat::Defense).into()],
+            ItemType::Armor,
+            25,
+        );
+
+        let mut inv: Inventory = Inventory::new();
+
+        // add dumby items to the inventory
+        inv.add_item(5, &potion).unwrap();
+        inv.add_item(15, &mana_pot).unwrap();
+        inv.add_item(1, &sword).unwrap();
+        inv.add_item(1, &sheild).unwrap();
+        inv.add_money(100);
+
+        // verify that the search alg works and that the items are sucessfully
+        // sorted into the appropriate bag
+        assert!(inv.has_item("potion").is_some());
+        assert!(inv.has_item("mana_pot").is_some());
+        assert!(inv.has_item("sword").is_some());
+        assert!(inv.has_item("sheild").is_some());
+
+        // add duplicate items to verify that it updates the existing Entry instead
+        // of making a new one
+        assert_eq!(inv.add_item(5, &potion).unwrap().get_qty(), 10);
+        assert!(inv.bags.consumable_bag.len() == 2);
+
+        // tests to see if the it handles the removal of more money that is gon hand
+        inv.rm_money(600);
+        assert!(inv.get_wallet() == 0);
+
+        // removing more than is currently in inventory is refused and leaves the
+        // entry alone, removing the exact qty drops the entry
+        assert!(inv.rm_item(16, "mana_pot").is_err());
+        assert!(inv.bags.consumable_bag.len() == 2);
+
+        inv.rm_item(15, "mana_pot").unwrap();
+        assert!(inv.bags.consumable_bag.len() == 1);
+
+        inv.rm_item(5, "potion").unwrap();
+        assert!(inv.has_item("potion").is_some());
+
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("inventory.json");
+        inv.save(&path).unwrap();
+        let inv2 = Inventory::load(&path).unwrap();
+        let invstr = serde_json::to_string_pretty(&inv).unwrap();
+        let inv2str = serde_json::to_string_pretty(&inv2).unwrap();
+
+        assert!(inv == inv2);
+        assert!(invstr == inv2str);
+
+        // end inventory test
+        //******************************************************************************
+    }
+
+    #[test]
+    fn items_are_told_apart_by_id() {
+        let cookie = |value: i32| {
+            Item::new(
+                ItemId::new("cookie"),
+                String::from("Cookie"),
+                format!("Sells for {}", value),
+                vec![Effect::Heal(6).into()],
+                ItemType::Consumable,
+                value,
+            )
+        };
+
+        // a cookie whose price changed is still a cookie
+        let mut inv = Inventory::new();
+        inv.add_item(2, &cookie(4)).unwrap();
+        inv.add_item(3, &cookie(5)).unwrap();
+        assert_eq!(inv.get_entries().count(), 1);
+
+        let holding = inv.has_item("cookie").unwrap();
+        assert_eq!(holding.get_type(), ItemType::Consumable);
+        assert_eq!(holding.get_index(), 0);
+        assert_eq!(holding.get_qty(), 5);
+        assert_eq!(inv.has_item("bread_roll"), None);
+
+        let removal = inv.rm_item(2, "cookie").unwrap();
+        assert_eq!(removal.get_id().as_str(), "cookie");
+        assert_eq!((removal.get_removed(), removal.get_remaining()), (2, 3));
+        assert!(!removal.is_gone());
+
+        let removal = inv.rm_item(3, "cookie").unwrap();
+        assert_eq!((removal.get_removed(), removal.get_remaining()), (3, 0));
+        assert!(removal.is_gone());
+        assert!(inv.has_item("cookie").is_none());
+    }
+
+    #[test]
+    fn slots_and_stacks_are_limited() {
+        let item = |id: &str, max_stack: i32| {
+            let mut item = Item::new(
+                ItemId::new(id),
+                String::from(id),
+                String::new(),
+                vec![Effect::Heal(6).into()],
+                ItemType::Consumable,
+                4,
+            );
+            item.max_stack(max_stack);
+            item
+        };
+
+        let mut inv = Inventory::with_capacity(2);
+        inv.add_item(8, &item("cookie", 9)).unwrap();
+        assert_eq!(
+            inv.add_item(2, &item("cookie", 9)),
+            Err(InventoryError::Full {
+                id: ItemId::new("cookie"),
+                space: 1,
+            })
+        );
+        inv.add_item(1, &item("cookie", 9)).unwrap();
+
+        // a single bat is too many for a new slot that only holds one
+        assert_eq!(
+            inv.add_item(2, &item("bat", 1)),
+            Err(InventoryError::Full {
+                id: ItemId::new("bat"),
+                space: 1,
+            })
+        );
+        inv.add_item(1, &item("bat", 1)).unwrap();
+        assert_eq!(inv.get_used(), 2);
+
+        // both slots are taken
+        assert_eq!(
+            inv.add_item(1, &item("bread_roll", 9)),
+            Err(InventoryError::Full {
+                id: ItemId::new("bread_roll"),
+                space: 0,
+            })
+        );
+        assert_eq!(inv.get_used(), 2);
+    }
+
+    #[test]
+    fn bad_quantities_are_errors() {
+        let cookie = Item::new(
+            ItemId::new("cookie"),
+            String::from("Cookie"),
+            String::new(),
+            vec![Effect::Heal(6).into()],
+            ItemType::Consumable,
+            4,
+        );
+
+        let mut inv = Inventory::new();
+        assert_eq!(
+            inv.add_item(-3, &cookie),
+            Err(InventoryError::InvalidQuantity(-3))
+        );
+        assert_eq!(inv.get_used(), 0);
+
+        inv.add_item(2, &cookie).unwrap();
+        assert_eq!(
+            inv.rm_item(0, "cookie"),
+            Err(InventoryError::InvalidQuantity(0))
+        );
+        assert_eq!(
+            inv.rm_item(1, "bread_roll"),
+            Err(InventoryError::NotFound(ItemId::new("bread_roll")))
+        );
+
+        let error = inv.rm_item(3, "cookie").unwrap_err();
+        assert_eq!(
+            error,
+            InventoryError::Insufficient {
+                id: ItemId::new("cookie"),
+                held: 2,
+                wanted: 3,
+            }
+        );
+        assert_eq!(error.to_string(), "wanted 3 cookie but only 2 are held");
+        assert_eq!(inv.has_item("cookie").unwrap().get_qty(), 2);
+    }
+
+    #[test]
+    fn entries_refer_to_items_by_id() {
+        let mut items = ItemRegistry::new();
+        let cookie = Item::new(
+            ItemId::new("cookie"),
+            String::from("Cookie"),
+            String::from("A tasty treat"),
+            vec![Effect::Heal(6).into()],
+            ItemType::Consumable,
+            4,
+        );
+        items.insert(cookie.clone()).unwrap();
+
+        let mut inv = Inventory::new();
+        inv.add_item(2, &cookie).unwrap();
+        let entry = inv.get_entries().next().unwrap();
+        assert_eq!(entry.get_item(&items), Some(&cookie));
+
+        // the item itself is not written out, only its id
+        let json = serde_json::to_string(&inv).unwrap();
+        assert!(json.contains(r#"{"qty":2,"id":"cookie"}"#), "{}", json);
+        assert!(!json.contains("A tasty treat"), "{}", json);
+
+        assert_eq!(inv.check(&items), Ok(()));
+        let error = inv.check(&ItemRegistry::new()).unwrap_err();
+        assert!(error.contains("cookie"), "{}", error);
+    }
+}
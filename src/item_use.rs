@@ -0,0 +1,531 @@
+//! Using items on party members. The item's effects are applied to the
+//! target, consumables are used up, and what happened is handed back so
+//! battles and menus can tell the player
+
+use std::error::Error;
+use std::fmt;
+
+use rand::Rng;
+
+use character::Character;
+use inventory::{Inventory, InventoryError};
+use items::{Effect, ItemEffect, ItemId, ItemRegistry, ItemType};
+use stats::Stat;
+use status::Status;
+
+/// What one of an item's effects did to its target
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EffectResult {
+    // HP recovered, which can be less than the item heals at full health
+    Healed(i32),
+    // HP lost, and whether that made the target faint
+    Damaged { amount: i32, fainted: bool },
+    Raised(Stat, i32),
+    Lowered(Stat, i32),
+    Inflicted(Status),
+    Cured(Status),
+    // the target already fainted earlier in the item, or had nothing to cure
+    NoEffect,
+    // the effect's chance did not come up
+    Missed,
+}
+
+/// Everything that happened when an item was used
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemOutcome {
+    user: String,
+    target: String,
+    id: ItemId,
+    // the item's name, for messages
+    item: String,
+    // one for each of the item's effects, in order
+    results: Vec<EffectResult>,
+}
+
+impl ItemOutcome {
+    // Getters
+    pub fn get_user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn get_target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn get_id(&self) -> &ItemId {
+        &self.id
+    }
+
+    pub fn get_results(&self) -> &[EffectResult] {
+        &self.results
+    }
+
+    /// Describes what happened, for the battle log or a dialogue box
+    pub fn get_message(&self) -> String {
+        let used = if self.user == self.target {
+            format!("{} used the {}.", self.user, self.item)
+        } else {
+            format!("{} used the {} on {}.", self.user, self.item, self.target)
+        };
+
+        let mut message = used;
+        for result in &self.results {
+            message.push(' ');
+            message.push_str(&self.describe(*result));
+        }
+        message
+    }
+
+    fn describe(&self, result: EffectResult) -> String {
+        let target = &self.target;
+        match result {
+            EffectResult::Healed(0) => "Nothing happened.".to_string(),
+            EffectResult::Healed(amount) => format!("{} recovered {} HP!", target, amount),
+            EffectResult::Damaged {
+                amount,
+                fainted: true,
+            } => format!("{} took {} damage and fainted!", target, amount),
+            EffectResult::Damaged { amount, .. } => format!("{} took {} damage!", target, amount),
+            EffectResult::Raised(stat, amount) => {
+                format!("{}'s {} went up by {}!", target, stat.get_name(), amount)
+            }
+            EffectResult::Lowered(stat, amount) => {
+                format!("{}'s {} went down by {}!", target, stat.get_name(), amount)
+            }
+            EffectResult::Inflicted(status) => format!("{} is {}!", target, status.get_name()),
+            EffectResult::Cured(status) => {
+                format!("{} is no longer {}!", target, status.get_name())
+            }
+            EffectResult::NoEffect | EffectResult::Missed => {
+                format!("It had no effect on {}.", target)
+            }
+        }
+    }
+}
+
+/// Why an item could not be used
+#[derive(Debug, Clone, PartialEq)]
+pub enum UseError {
+    // there is no party member with the name
+    NoCharacter(String),
+    // the user has fainted and cannot act
+    Unconscious(String),
+    // the user has a status that keeps them from acting, ie sleep
+    Incapacitated { name: String, status: Status },
+    // the target has fainted, and the item cannot help them
+    TargetFainted(String),
+    // the item is not in the registry
+    UnknownItem(ItemId),
+    // weapons and armor are equipped, not used
+    NotUsable(ItemId),
+    // the item is not in the inventory
+    Inventory(InventoryError),
+}
+
+impl From<InventoryError> for UseError {
+    fn from(error: InventoryError) -> UseError {
+        UseError::Inventory(error)
+    }
+}
+
+impl fmt::Display for UseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UseError::NoCharacter(ref name) => write!(f, "there is no {} in the party", name),
+            UseError::Unconscious(ref name) => write!(f, "{} has fainted and cannot act", name),
+            UseError::Incapacitated { ref name, status } => {
+                write!(f, "{} is {} and cannot act", name, status.get_name())
+            }
+            UseError::TargetFainted(ref name) => write!(f, "{} has fainted", name),
+            UseError::UnknownItem(ref id) => write!(f, "there is no item {}", id),
+            UseError::NotUsable(ref id) => write!(f, "{} cannot be used", id),
+            UseError::Inventory(ref error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for UseError {}
+
+/// Has one party member use an item from the inventory on another, or on
+/// themselves. Each of the item's effects rolls its chance with rng. Nothing
+/// changes if there is an error
+pub fn use_item<R: Rng>(
+    party: &mut [Character],
+    inventory: &mut Inventory,
+    items: &ItemRegistry,
+    rng: &mut R,
+    user: &str,
+    target: &str,
+    id: &str,
+) -> Result<ItemOutcome, UseError> {
+    let find = |name: &str| {
+        party
+            .iter()
+            .position(|character| character.get_name() == name)
+            .ok_or_else(|| UseError::NoCharacter(name.to_string()))
+    };
+    let (user, target) = (find(user)?, find(target)?);
+
+    if !party[user].can_act() {
+        let name = party[user].get_name().to_string();
+        return Err(match party[user].get_statuses().get_incapacitating() {
+            Some(status) if party[user].is_conscious() => UseError::Incapacitated { name, status },
+            _ => UseError::Unconscious(name),
+        });
+    }
+
+    let item = match items.get(id) {
+        Some(item) => item,
+        None => return Err(UseError::UnknownItem(ItemId::new(id))),
+    };
+    if item.get_type() != ItemType::Consumable {
+        return Err(UseError::NotUsable(ItemId::new(id)));
+    }
+    if inventory.has_item(id).is_none() {
+        return Err(InventoryError::NotFound(ItemId::new(id)).into());
+    }
+    if !party[target].is_conscious() {
+        return Err(UseError::TargetFainted(
+            party[target].get_name().to_string(),
+        ));
+    }
+
+    let results = item
+        .get_effects()
+        .iter()
+        .map(|effect| apply(&mut party[target], effect, rng))
+        .collect();
+
+    inventory.rm_item(1, id)?;
+
+    Ok(ItemOutcome {
+        user: party[user].get_name().to_string(),
+        target: party[target].get_name().to_string(),
+        id: item.get_id().clone(),
+        item: item.get_name(),
+        results,
+    })
+}
+
+/// Applies one effect of an item to a character, if its chance comes up
+fn apply<R: Rng>(character: &mut Character, effect: &ItemEffect, rng: &mut R) -> EffectResult {
+    if effect.get_chance() < 1.0 && rng.next_f64() >= effect.get_chance() {
+        return EffectResult::Missed;
+    }
+    // an earlier effect may have made them faint
+    if !character.is_conscious() {
+        return EffectResult::NoEffect;
+    }
+
+    let duration = effect.get_duration();
+    match *effect.get_effect() {
+        Effect::Heal(amount) => EffectResult::Healed(character.heal(amount)),
+        Effect::Damage(amount) => EffectResult::Damaged {
+            amount: character.hurt(amount),
+            fainted: !character.is_conscious(),
+        },
+        Effect::Buff(amount, stat) => {
+            character.modify(stat, amount, duration);
+            EffectResult::Raised(stat, amount)
+        }
+        Effect::Debuff(amount, stat) => {
+            character.modify(stat, -amount, duration);
+            EffectResult::Lowered(stat, amount)
+        }
+        Effect::Inflict(status) => {
+            character.inflict(status, duration);
+            EffectResult::Inflicted(status)
+        }
+        Effect::Cure(status) => {
+            if character.cure(status) {
+                EffectResult::Cured(status)
+            } else {
+                EffectResult::NoEffect
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{SeedableRng, XorShiftRng};
+
+    use game::tests::{inventory, items};
+    use items::Item;
+    use stats::{Duration, Stats};
+
+    fn character(name: &str, hp: i32) -> Character {
+        let mut base = Stats::new();
+        base.set(Stat::Hp, hp);
+        base.set(Stat::Defense, 2);
+
+        let mut character = Character::new(name);
+        character.base(base);
+        character.restore();
+        character
+    }
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([1, 2, 3, 4])
+    }
+
+    #[test]
+    fn healing_items_are_used_up() {
+        let items = items();
+        let mut party = vec![character("Ness", 30), character("Paula", 24)];
+        let mut inventory = inventory(&items, &["cookie", "hamburger"]);
+        party[1].hurt(20);
+
+        let outcome = use_item(
+            &mut party,
+            &mut inventory,
+            &items,
+            &mut rng(),
+            "Ness",
+            "Paula",
+            "cookie",
+        )
+        .unwrap();
+        assert_eq!(outcome.get_results(), &[EffectResult::Healed(6)]);
+        assert_eq!(
+            outcome.get_message(),
+            "Ness used the Cookie on Paula. Paula recovered 6 HP!"
+        );
+        assert_eq!(party[1].get_hp(), 10);
+        assert!(inventory.has_item("cookie").is_none());
+
+        // more than is missing only heals what is missing
+        let outcome = use_item(
+            &mut party,
+            &mut inventory,
+            &items,
+            &mut rng(),
+            "Paula",
+            "Paula",
+            "hamburger",
+        )
+        .unwrap();
+        assert_eq!(outcome.get_results(), &[EffectResult::Healed(14)]);
+        assert_eq!(
+            outcome.get_message(),
+            "Paula used the Hamburger. Paula recovered 14 HP!"
+        );
+    }
+
+    #[test]
+    fn bad_uses_change_nothing() {
+        let items = items();
+        let mut party = vec![character("Ness", 30), character("Paula", 24)];
+        let mut inventory = inventory(&items, &["cookie", "cracked_bat"]);
+
+        let mut try_use = |user: &str, target: &str, id: &str| {
+            use_item(
+                &mut party,
+                &mut inventory,
+                &items,
+                &mut rng(),
+                user,
+                target,
+                id,
+            )
+            .unwrap_err()
+        };
+        assert_eq!(
+            try_use("Jeff", "Ness", "cookie"),
+            UseError::NoCharacter("Jeff".to_string())
+        );
+        assert_eq!(
+            try_use("Ness", "Ness", "cracked_bat"),
+            UseError::NotUsable(ItemId::new("cracked_bat"))
+        );
+        assert_eq!(
+            try_use("Ness", "Ness", "bread_roll"),
+            UseError::Inventory(InventoryError::NotFound(ItemId::new("bread_roll")))
+        );
+        assert_eq!(
+            try_use("Ness", "Ness", "magic_truffle"),
+            UseError::UnknownItem(ItemId::new("magic_truffle"))
+        );
+
+        party[1].hurt(24);
+        assert_eq!(
+            use_item(
+                &mut party,
+                &mut inventory,
+                &items,
+                &mut rng(),
+                "Ness",
+                "Paula",
+                "cookie"
+            ),
+            Err(UseError::TargetFainted("Paula".to_string()))
+        );
+        assert_eq!(
+            use_item(
+                &mut party,
+                &mut inventory,
+                &items,
+                &mut rng(),
+                "Paula",
+                "Ness",
+                "cookie"
+            ),
+            Err(UseError::Unconscious("Paula".to_string()))
+        );
+
+        party[0].inflict(Status::Sleep, None);
+        let error = use_item(
+            &mut party,
+            &mut inventory,
+            &items,
+            &mut rng(),
+            "Ness",
+            "Ness",
+            "cookie",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            UseError::Incapacitated {
+                name: "Ness".to_string(),
+                status: Status::Sleep,
+            }
+        );
+        assert_eq!(error.to_string(), "Ness is asleep and cannot act");
+        assert_eq!(inventory.has_item("cookie").unwrap().get_qty(), 1);
+    }
+
+    #[test]
+    fn stat_and_damage_items_hit_the_target() {
+        let mut items = items();
+        items
+            .insert(Item::new(
+                ItemId::new("bad_eclair"),
+                String::from("Bad Eclair"),
+                String::from("It has gone off."),
+                vec![Effect::Damage(40).into()],
+                ItemType::Consumable,
+                1,
+            ))
+            .unwrap();
+        let mut party = vec![character("Ness", 30)];
+        let mut inventory = inventory(&items, &["defense_spray", "bad_eclair"]);
+
+        let outcome = use_item(
+            &mut party,
+            &mut inventory,
+            &items,
+            &mut rng(),
+            "Ness",
+            "Ness",
+            "defense_spray",
+        )
+        .unwrap();
+        assert_eq!(
+            outcome.get_results(),
+            &[EffectResult::Raised(Stat::Defense, 10)]
+        );
+        assert_eq!(
+            outcome.get_message(),
+            "Ness used the Defense Spray. Ness's Defense went up by 10!"
+        );
+        assert_eq!(party[0].get_stat(Stat::Defense), 12);
+        assert_eq!(
+            party[0].get_modifiers()[0].get_duration(),
+            Some(Duration::Turns(3))
+        );
+
+        let outcome = use_item(
+            &mut party,
+            &mut inventory,
+            &items,
+            &mut rng(),
+            "Ness",
+            "Ness",
+            "bad_eclair",
+        )
+        .unwrap();
+        assert_eq!(
+            outcome.get_results(),
+            &[EffectResult::Damaged {
+                amount: 30,
+                fainted: true,
+            }]
+        );
+        assert!(!party[0].is_conscious());
+        assert_eq!(inventory.get_used(), 0);
+    }
+
+    #[test]
+    fn every_effect_is_applied_if_its_chance_comes_up() {
+        let mut items = items();
+        let mut poison = ItemEffect::new(Effect::Inflict(Status::Poison));
+        poison.chance(0.0);
+        items
+            .insert(Item::new(
+                ItemId::new("stale_eclair"),
+                String::from("Stale Eclair"),
+                String::from("It might be fine."),
+                vec![Effect::Heal(10).into(), poison],
+                ItemType::Consumable,
+                1,
+            ))
+            .unwrap();
+        let mut party = vec![character("Ness", 30)];
+        let mut inventory = inventory(
+            &items,
+            &[
+                "stale_eclair",
+                "skip_sandwich",
+                "refreshing_herb",
+                "refreshing_herb",
+            ],
+        );
+        party[0].hurt(20);
+
+        let mut rng = rng();
+        let mut eat = |party: &mut [Character], id: &str| {
+            use_item(party, &mut inventory, &items, &mut rng, "Ness", "Ness", id).unwrap()
+        };
+
+        let outcome = eat(&mut party, "stale_eclair");
+        assert_eq!(
+            outcome.get_results(),
+            &[EffectResult::Healed(10), EffectResult::Missed]
+        );
+        assert_eq!(
+            outcome.get_message(),
+            "Ness used the Stale Eclair. Ness recovered 10 HP! It had no effect on Ness."
+        );
+        assert!(!party[0].has_status(Status::Poison));
+
+        // the speed boost lasts for a while out of battle
+        let outcome = eat(&mut party, "skip_sandwich");
+        assert_eq!(
+            outcome.get_results(),
+            &[
+                EffectResult::Healed(10),
+                EffectResult::Raised(Stat::Speed, 10),
+            ]
+        );
+        assert_eq!(
+            party[0].get_modifiers()[0].get_duration(),
+            Some(Duration::Seconds(30.0))
+        );
+
+        party[0].inflict(Status::Poison, None);
+        let outcome = eat(&mut party, "refreshing_herb");
+        assert_eq!(
+            outcome.get_results(),
+            &[EffectResult::Cured(Status::Poison)]
+        );
+        assert_eq!(
+            outcome.get_message(),
+            "Ness used the Refreshing Herb. Ness is no longer poisoned!"
+        );
+        let outcome = eat(&mut party, "refreshing_herb");
+        assert_eq!(outcome.get_results(), &[EffectResult::NoEffect]);
+    }
+}
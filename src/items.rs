@@ -1,6 +1,68 @@
 /* items.rs */
 #![deny(missing_docs)]
-//! Defines how an item is represented
+//! Defines how an item is represented, and the registry every item in the
+//! game is loaded into
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::path::Path;
+
+use serde_json;
+
+use equipment::EquipSlot;
+use stats::{Duration, Stat};
+use status::Status;
+
+/// How many of an item fit in one inventory slot, unless the item says
+/// otherwise
+pub const DEFAULT_MAX_STACK: i32 = 99;
+
+/// Gives items without a max_stack in their file the default
+fn default_max_stack() -> i32 {
+    DEFAULT_MAX_STACK
+}
+
+/// Gives effects without a chance in their file the default of always
+fn always() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+/// The stable name an item is known by in data files and saves, ie "cookie".
+/// Unlike the display name it never changes once items have been saved
+pub struct ItemId(String);
+
+impl ItemId {
+    /// Creates an item id
+    pub fn new(id: &str) -> ItemId {
+        ItemId(id.to_string())
+    }
+
+    /// Returns the id as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'a> From<&'a str> for ItemId {
+    fn from(id: &'a str) -> ItemId {
+        ItemId::new(id)
+    }
+}
+
+impl Borrow<str> for ItemId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ItemId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Specifies the type of the item
@@ -15,67 +77,152 @@ pub enum ItemType {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-/// Specifies the effect of the item and the modifier. All hold an i32 that
-/// represents the number to modify the stats by, except the Buff and Debuff
-/// variants, which also hold a String. This string is used for pattern matching
-/// when determining which stat it effects
+/// Specifies the effect of the item and the modifier. Most hold an i32 that
+/// represents the number to modify the stats by. The Buff and Debuff
+/// variants also hold the Stat they effect, and the Inflict and Cure variants
+/// only hold a Status
 pub enum Effect {
     /// Increases the targets HP
     Heal(i32),
     /// Reduces the targets HP
     Damage(i32),
     /// Increases one of the targets stats. Takes an i32 to represent the amount
-    /// of the buff and a String to specify which stat
-    Buff(i32, String),
+    /// of the buff and the Stat it raises
+    Buff(i32, Stat),
     /// Decreases one of the targets stats. Takes an i32 to represent the amount
-    /// of the debuff and a String to specify which stat
-    Debuff(i32, String),
+    /// of the debuff and the Stat it lowers
+    Debuff(i32, Stat),
+    /// Gives the target a status, ie poison
+    Inflict(Status),
+    /// Takes a status away from the target
+    Cure(Status),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// One of the effects an item has, with how long it lasts and how likely it
+/// is to work
+pub struct ItemEffect {
+    /// What the effect does
+    effect: Effect,
+    /// How long a Buff, Debuff or Inflict lasts. None lasts until the battle
+    /// ends or the status is cured. Other effects happen at once and ignore it
+    #[serde(default)]
+    duration: Option<Duration>,
+    /// The chance of the effect working, from 0 to 1
+    #[serde(default = "always")]
+    chance: f64,
+}
+
+// Related functions
+impl ItemEffect {
+    /// Creates an effect that always works and has no duration
+    pub fn new(effect: Effect) -> ItemEffect {
+        ItemEffect {
+            effect,
+            duration: None,
+            chance: 1.0,
+        }
+    }
+}
+
+impl From<Effect> for ItemEffect {
+    fn from(effect: Effect) -> ItemEffect {
+        ItemEffect::new(effect)
+    }
+}
+
+// ItemEffect methods
+impl ItemEffect {
+    /// Returns what the effect does
+    pub fn get_effect(&self) -> &Effect {
+        &self.effect
+    }
+    /// Returns how long the effect lasts
+    pub fn get_duration(&self) -> Option<Duration> {
+        self.duration
+    }
+    /// Returns the chance of the effect working
+    pub fn get_chance(&self) -> f64 {
+        self.chance
+    }
+    /// Sets how long the effect lasts
+    pub fn duration(&mut self, duration: Duration) {
+        self.duration = Some(duration);
+    }
+    /// Sets the chance of the effect working, from 0 to 1
+    pub fn chance(&mut self, chance: f64) {
+        self.chance = chance;
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// A struct that represents all items in the game
 pub struct Item {
+    /// The id the item is registered under. Item files give it as the key of
+    /// each item rather than as a field
+    #[serde(skip)]
+    id: ItemId,
     /// The name of the item
     name: String,
     /// The way the item is described to the player
     description: String,
-    /// The effect the item has on a target
-    effect: Effect,
+    /// The effects the item has on a target, in the order they happen
+    effects: Vec<ItemEffect>,
     /// The type of the item
     item_type: ItemType,
     /// What the item is sold for
     value: i32,
+    /// The most of the item that can be carried in one inventory slot
+    #[serde(default = "default_max_stack")]
+    max_stack: i32,
+    /// Where armor is worn. Armor without a slot is worn on the body, other
+    /// item types ignore it
+    #[serde(default)]
+    slot: Option<EquipSlot>,
+    /// The names of the characters who can equip the item. Anyone can if
+    /// it is empty
+    #[serde(default)]
+    equippable_by: Vec<String>,
 }
 
 // Related functions
 impl Item {
     /// Creates a new item
     pub fn new(
+        id: ItemId,
         name: String,
         description: String,
-        effect: Effect,
+        effects: Vec<ItemEffect>,
         item_type: ItemType,
         value: i32,
     ) -> Item {
         Item {
+            id,
             name,
             description,
-            effect,
+            effects,
             item_type,
             value,
+            max_stack: DEFAULT_MAX_STACK,
+            slot: None,
+            equippable_by: Vec::new(),
         }
     }
 }
 
 // Item methods
 impl Item {
+    /// Returns the id of an item
+    pub fn get_id(&self) -> &ItemId {
+        &self.id
+    }
     /// Returns the name of an item
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
-    /// Returns the effect of an item
-    pub fn get_effect(&self) -> Effect {
-        self.effect.clone()
+    /// Returns the effects of an item
+    pub fn get_effects(&self) -> &[ItemEffect] {
+        &self.effects
     }
     /// Returns the description of an item
     pub fn get_description(&self) -> String {
@@ -89,4 +236,265 @@ impl Item {
     pub fn get_value(&self) -> i32 {
         self.value
     }
+    /// Returns the most of an item one inventory slot holds
+    pub fn get_max_stack(&self) -> i32 {
+        self.max_stack
+    }
+    /// Sets the most of an item one inventory slot holds
+    pub fn max_stack(&mut self, max_stack: i32) {
+        self.max_stack = max_stack;
+    }
+    /// Returns where the item is worn, or None if it cannot be equipped
+    pub fn get_slot(&self) -> Option<EquipSlot> {
+        match self.item_type {
+            ItemType::Weapon => Some(EquipSlot::Weapon),
+            ItemType::Armor => Some(self.slot.unwrap_or(EquipSlot::Body)),
+            ItemType::Consumable => None,
+        }
+    }
+    /// Sets where armor is worn
+    pub fn slot(&mut self, slot: EquipSlot) {
+        self.slot = Some(slot);
+    }
+    /// Determines if a character is allowed to equip the item
+    pub fn can_equip(&self, character: &str) -> bool {
+        self.equippable_by.is_empty() || self.equippable_by.iter().any(|name| name == character)
+    }
+    /// Limits the item to being equipped by some characters
+    pub fn equippable_by(&mut self, characters: Vec<String>) {
+        self.equippable_by = characters;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Every item in the game, keyed by id. Items are defined in json files so
+/// new ones can be added without recompiling. Each file maps ids to items:
+///
+/// ```json
+/// {
+///     "cookie": {
+///         "name": "Cookie",
+///         "description": "A freshly baked cookie.",
+///         "effects": [{ "effect": { "Heal": 6 } }],
+///         "item_type": "Consumable",
+///         "value": 4
+///     }
+/// }
+/// ```
+pub struct ItemRegistry {
+    /// The items, sorted by id
+    items: BTreeMap<ItemId, Item>,
+}
+
+// Related functions
+impl ItemRegistry {
+    /// Returns an empty registry
+    pub fn new() -> ItemRegistry {
+        ItemRegistry {
+            items: BTreeMap::new(),
+        }
+    }
+
+    /// Loads every json file in a directory into a registry. An id defined
+    /// twice is an error, even across files
+    pub fn load(directory: &Path) -> Result<ItemRegistry, String> {
+        let entries =
+            fs::read_dir(directory).map_err(|error| format!("{:?}: {}", directory, error))?;
+
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|error| format!("{:?}: {}", directory, error))?
+                .path();
+            if path.extension() == Some("json".as_ref()) {
+                paths.push(path);
+            }
+        }
+        // so duplicates are always reported against the same file
+        paths.sort();
+
+        let mut registry = ItemRegistry::new();
+        for path in paths {
+            registry.load_file(&path)?;
+        }
+        Ok(registry)
+    }
+}
+
+// ItemRegistry methods
+impl ItemRegistry {
+    /// Adds the items defined in a json file
+    pub fn load_file(&mut self, path: &Path) -> Result<(), String> {
+        let file = File::open(path).map_err(|error| format!("{:?}: {}", path, error))?;
+        let items: BTreeMap<String, Item> =
+            serde_json::from_reader(file).map_err(|error| format!("{:?}: {}", path, error))?;
+
+        for (id, mut item) in items {
+            item.id = ItemId::new(&id);
+            self.insert(item)
+                .map_err(|error| format!("{:?}: {}", path, error))?;
+        }
+        Ok(())
+    }
+
+    /// Adds an item under its id. Fails if the id is already taken, the item
+    /// could never be carried, or one of its effects has a chance that is not
+    /// between 0 and 1
+    pub fn insert(&mut self, item: Item) -> Result<(), String> {
+        if self.items.contains_key(item.get_id()) {
+            return Err(format!(
+                "there is already an item {:?}",
+                item.get_id().as_str()
+            ));
+        }
+        if item.get_max_stack() < 1 {
+            return Err(format!(
+                "item {:?} has a max stack of {}",
+                item.get_id().as_str(),
+                item.get_max_stack()
+            ));
+        }
+        // NaN is not in the range either
+        if let Some(effect) = item
+            .get_effects()
+            .iter()
+            .find(|effect| !(0.0..=1.0).contains(&effect.get_chance()))
+        {
+            return Err(format!(
+                "item {:?} has an effect with a chance of {}",
+                item.get_id().as_str(),
+                effect.get_chance()
+            ));
+        }
+
+        self.items.insert(item.get_id().clone(), item);
+        Ok(())
+    }
+
+    /// Returns the item with an id
+    pub fn get(&self, id: &str) -> Option<&Item> {
+        self.items.get(id)
+    }
+
+    /// Determines if an item with an id exists
+    pub fn contains(&self, id: &str) -> bool {
+        self.items.contains_key(id)
+    }
+
+    /// Returns every item, sorted by id
+    pub fn get_items(&self) -> impl Iterator<Item = &Item> {
+        self.items.values()
+    }
+
+    /// Returns how many items there are
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Determines if there are no items
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::TempDir;
+
+    fn assets() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/items"))
+    }
+
+    #[test]
+    fn items_are_loaded_from_the_assets() {
+        let registry = ItemRegistry::load(assets()).unwrap();
+
+        let cookie = registry.get("cookie").unwrap();
+        assert_eq!(cookie.get_id().as_str(), "cookie");
+        assert_eq!(cookie.get_name(), "Cookie");
+        assert_eq!(cookie.get_effects(), &[ItemEffect::new(Effect::Heal(6))]);
+        assert_eq!(cookie.get_type(), ItemType::Consumable);
+
+        assert_eq!(cookie.get_max_stack(), DEFAULT_MAX_STACK);
+
+        // items can do several things, for a while or only some of the time
+        let sandwich = registry.get("skip_sandwich").unwrap().get_effects();
+        assert_eq!(sandwich.len(), 2);
+        assert_eq!(sandwich[1].get_duration(), Some(Duration::Seconds(30.0)));
+        assert_eq!(sandwich[1].get_chance(), 1.0);
+        let eclair = registry.get("rotten_eclair").unwrap().get_effects();
+        assert_eq!(eclair[1].get_effect(), &Effect::Inflict(Status::Poison));
+        assert_eq!(eclair[1].get_chance(), 0.5);
+
+        let bat = registry.get("baseball_bat").unwrap();
+        assert_eq!(bat.get_type(), ItemType::Weapon);
+        assert_eq!(bat.get_max_stack(), 1);
+        assert_eq!(bat.get_slot(), Some(EquipSlot::Weapon));
+        assert!(bat.can_equip("Ness") && !bat.can_equip("Paula"));
+
+        let cap = registry.get("baseball_cap").unwrap();
+        assert_eq!(cap.get_slot(), Some(EquipSlot::Other));
+        assert!(cap.can_equip("Paula"));
+        assert_eq!(cookie.get_slot(), None);
+        assert!(registry.contains("bread_roll"));
+        assert!(registry.get("Cookie").is_none());
+    }
+
+    #[test]
+    fn bad_item_files_are_errors() {
+        let directory = TempDir::new().unwrap();
+        let write = |name: &str, json: &str| fs::write(directory.path().join(name), json).unwrap();
+
+        let cookie = r#"{ "cookie": { "name": "Cookie", "description": "", "effects": [{ "effect": { "Heal": 6 } }], "item_type": "Consumable", "value": 4 } }"#;
+        write("a.json", cookie);
+        // only json files are read
+        write("notes.txt", "not an item");
+        assert_eq!(ItemRegistry::load(directory.path()).unwrap().len(), 1);
+
+        write("b.json", cookie);
+        let error = ItemRegistry::load(directory.path()).unwrap_err();
+        assert!(
+            error.contains("b.json") && error.contains("cookie"),
+            "{}",
+            error
+        );
+
+        write("b.json", r#"{ "bread": { "name": "Bread" } }"#);
+        let error = ItemRegistry::load(directory.path()).unwrap_err();
+        assert!(error.contains("b.json"), "{}", error);
+
+        write(
+            "b.json",
+            &cookie
+                .replace("cookie", "crumb")
+                .replace("4 }", "4, \"max_stack\": 0 }"),
+        );
+        let error = ItemRegistry::load(directory.path()).unwrap_err();
+        assert!(error.contains("max stack"), "{}", error);
+
+        for chance in &["1.5", "-0.1"] {
+            write(
+                "b.json",
+                &cookie
+                    .replace("cookie", "crumb")
+                    .replace("6 }", &format!("6 }}, \"chance\": {}", chance)),
+            );
+            let error = ItemRegistry::load(directory.path()).unwrap_err();
+            assert!(error.contains("chance"), "{}", error);
+        }
+
+        let mut effect = ItemEffect::new(Effect::Heal(6));
+        effect.chance(f64::NAN);
+        let crumb = Item::new(
+            ItemId::new("crumb"),
+            String::from("Crumb"),
+            String::new(),
+            vec![effect],
+            ItemType::Consumable,
+            1,
+        );
+        assert!(ItemRegistry::new().insert(crumb).is_err());
+    }
 }
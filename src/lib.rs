@@ -0,0 +1,44 @@
+//! Uranusbound: Fanmade remake of Earthbound
+
+// the serde_derive we are pinned to wraps its impls in a const
+#![allow(non_local_definitions)]
+
+extern crate dirs;
+extern crate find_folder;
+#[macro_use]
+extern crate glium;
+extern crate glium_graphics;
+extern crate graphics;
+extern crate image;
+extern crate piston;
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate tempfile;
+extern crate tiled;
+extern crate xml;
+
+pub mod animation;
+pub mod camera;
+pub mod character;
+pub mod chunk;
+pub mod collision;
+pub mod equipment;
+pub mod game;
+pub mod game_loop;
+pub mod input;
+pub mod inventory;
+pub mod item_use;
+pub mod items;
+pub mod map;
+pub mod objects;
+pub mod player;
+pub mod render;
+pub mod replay;
+pub mod save;
+pub mod scene;
+pub mod stats;
+pub mod status;
+pub mod world;
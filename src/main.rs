@@ -1,34 +1,38 @@
+extern crate find_folder;
 extern crate glium;
 extern crate glium_graphics;
-extern crate graphics;
 extern crate piston;
-extern crate tiled;
-
-extern crate image;
+extern crate uranusbound;
 
-// for integration with tiled
-//use tiled::parse_file;
+use std::env;
+use std::path::PathBuf;
 
 // for creating the window, textures, and rendering
-use glium_graphics::{Flip, Glium2d, GliumWindow, OpenGL, Texture, TextureSettings};
-use graphics::Transformed;
+use glium::Surface;
+use glium_graphics::{Glium2d, GliumWindow, OpenGL};
+use piston::event_loop::EventLoop;
 use piston::window::WindowSettings;
 
-// for convenience
-// renamed to avoid confusion
-extern crate find_folder;
-use graphics as piston_graphics;
-use piston_graphics::DrawState;
-
 // for handling events
-use piston::event_loop::EventLoop;
-use piston::input::{Button, CloseEvent, Key, PressEvent, RenderEvent, ResizeEvent};
-
-mod camera;
-use camera::{Camera, Tile};
-
-mod map;
-use map::Map;
+use piston::input::{
+    Button, ButtonEvent, CloseEvent, ControllerAxisEvent, FocusEvent, Key, RenderEvent, ResizeEvent,
+};
+
+use uranusbound::camera::Camera;
+use uranusbound::character::Character;
+use uranusbound::game::Game;
+use uranusbound::game_loop::GameLoop;
+use uranusbound::input::Bindings;
+use uranusbound::items::ItemRegistry;
+use uranusbound::player::SpriteSheet;
+use uranusbound::render::{GliumLoader, GliumRenderer, TextureLoader, TileRenderer};
+use uranusbound::save::{SaveGame, SaveSlots};
+use uranusbound::scene::{SceneStack, Title};
+use uranusbound::stats::{Stat, Stats};
+use uranusbound::world::World;
+
+/// The save slot the game is saved to on exit
+const AUTOSAVE: usize = 0;
 
 fn main() {
     let opengl = OpenGL::V3_2;
@@ -41,142 +45,184 @@ fn main() {
     println!("{:?}", &assets);
 
     // this is also the factory
-    let ref mut window: GliumWindow = WindowSettings::new("Uranusbound", [w, h])
+    let window: &mut GliumWindow = &mut WindowSettings::new("Uranusbound", [w, h])
         .exit_on_esc(true)
         .opengl(opengl)
         .resizable(false)
         .build()
         .unwrap();
 
+    // updates run at a fixed rate, frames are drawn as fast as allowed
+    let mut game_loop = GameLoop::new(60, 60);
+    window.set_event_settings(game_loop.get_event_settings());
+
     let mut g2d = Glium2d::new(opengl, window);
+    let mut tile_renderer = TileRenderer::new(window).unwrap();
 
-    let map = Map::load(assets.join("best_map_large.tmx"), window);
+    // maps keep loading textures as the player walks through doors
+    let mut loader = GliumLoader::new(window);
+    let sprites = loader
+        .load_texture_keyed(&assets.join("char_sample.png"), [0, 255, 0])
+        .unwrap();
 
     let (win_width, win_height) = window.get_max_viewport_dimensions();
 
-    let ref mut camera = Camera::load(80, 80, win_width as i32, win_height as i32);
+    let mut camera = Camera::load(80, 80, win_width as i32, win_height as i32);
     camera.tile_buffer_auto_reserve();
+    camera.deadzone(64, 48);
+    camera.smoothing(8.0);
+
+    // keeps a handful of maps around so doors can be walked back through quickly
+    let world = World::new(assets.clone(), 4, 512 * 1024 * 1024);
+
+    let mut game = Game::new(
+        world,
+        Box::new(loader),
+        sprites,
+        SpriteSheet::char_sample(0),
+        camera,
+    );
+    if let Err(error) = game.enter("best_map_large.tmx", "start") {
+        println!("Could not load the first map: {}", error);
+        return;
+    }
+
+    // designers add items by dropping json files in here
+    match ItemRegistry::load(&assets.join("items")) {
+        Ok(items) => game.items(items),
+        Err(error) => println!("Could not load the items: {}", error),
+    }
+
+    let (mut base, mut growth) = (Stats::new(), Stats::new());
+    for &(stat, start, gain) in &[
+        (Stat::Hp, 30, 6),
+        (Stat::Pp, 10, 3),
+        (Stat::Offense, 2, 2),
+        (Stat::Defense, 2, 1),
+        (Stat::Speed, 2, 1),
+        (Stat::Guts, 2, 1),
+        (Stat::Vitality, 2, 1),
+        (Stat::Iq, 2, 1),
+        (Stat::Luck, 2, 1),
+    ] {
+        base.set(stat, start);
+        growth.set(stat, gain);
+    }
+    let mut ness = Character::new("Ness");
+    ness.base(base);
+    ness.growth(growth);
+    ness.restore();
+    game.party(vec![ness]);
+
+    // the game is saved to the first slot on exit and picked back up from it
+    let slots = SaveSlots::in_data_dir(3);
+    if let Ok(ref slots) = slots {
+        if slots.is_used(AUTOSAVE) {
+            match slots
+                .load(AUTOSAVE)
+                .and_then(|save| save.restore(&mut game))
+            {
+                Ok(()) => println!("Continuing from {:?}", slots.get_path(AUTOSAVE)),
+                Err(error) => println!("Could not load the save: {}", error),
+            }
+        }
+    }
 
-    let tile_img = piston_graphics::image::Image::new();
+    // players can remap the controls by editing this file
+    match Bindings::load(&assets.join("bindings.json")) {
+        Ok(bindings) => game.get_mut_input().bindings(bindings),
+        Err(error) => println!("Using the default controls: {}", error),
+    }
+
+    let stack = &mut SceneStack::new(game, Box::new(Title::new()));
+
+    // `--record <file>` saves everything pressed, so a bug can be played back
+    // in a test
+    let args: Vec<String> = env::args().collect();
+    let record = args
+        .iter()
+        .position(|arg| arg == "--record")
+        .and_then(|index| args.get(index + 1))
+        .map(PathBuf::from);
+    if record.is_some() {
+        stack.get_mut_game().get_mut_input().record();
+    }
 
     // event loop
     'game_loop: while let Some(event) = window.next() {
-        // render event
-        if let Some(args) = event.render_args() {
-            let mut target = window.draw();
+        // update events step the scenes, render events give back how far
+        // between updates the frame is
+        let alpha = game_loop.event(stack, &event);
 
-            // get the dimensions so the render area scales with
-            // window size
-
-            g2d.draw(&mut target, args.viewport(), |context, frame| {
-                // iter through rows of map texture
-                piston_graphics::clear([0.0, 0.0, 0.0, 0.0], frame);
-
-                let (c_x, c_y, c_x_max, c_y_max) = camera.get_rect();
-
-                for (y, row) in map.get_map().layers[0]
-                    .tiles
-                    .iter()
-                    .enumerate()
-                    .filter(|(y, _)| c_y <= *y as i32 * 16 && *y as i32 * 16 <= c_y_max)
-                {
-                    for (x, &tile) in row
-                        .iter()
-                        .enumerate()
-                        .filter(|(x, _)| c_x <= *x as i32 * 16 && *x as i32 * 16 <= c_x_max)
-                    {
-                        println!("X: {}, Y: {}, Tile: {}", x, y, tile);
-                        // skip if tile is zero, we need to be one ahead of it
-                        if tile == 0 {
-                            continue;
-                        }
-
-                        let tile = tile - 1; // tiled counts from 1
-
-                        /*  of the particular tile in the tilesheet
-                            tile = tile index x_max is the width of the screen 
-                            y_max is the height of the screen
-                        */
-                        let tex_rect = [
-                            (tile as i32 % (map.get_width() / map.get_tile_width())
-                                * map.get_tile_width()) as f64, // x coordinate
-                            (tile as i32 / (map.get_width() / map.get_tile_height())
-                                * map.get_tile_height()) as f64, // y coordinate
-                            map.get_tile_width() as f64,
-                            map.get_tile_height() as f64,
-                        ];
-
-                        // Converts to the cartesian plane
-                        let trans = context.transform.trans(
-                            x as f64 * map.get_tile_width() as f64 - camera.get_x() as f64,
-                            y as f64 * map.get_tile_height() as f64 - camera.get_y() as f64,
-                        );
-
-                        tile_img.src_rect(tex_rect).draw(
-                            map.get_tile_sheet(),
-                            &DrawState::default(),
-                            trans,
-                            frame,
-                        );
-                    }
-                }
-            });
+        if let (Some(args), Some(alpha)) = (event.render_args(), alpha) {
+            let mut target = window.draw();
+            target.clear_color(0.0, 0.0, 0.0, 0.0);
+            tile_renderer.reset_stats();
+
+            stack.render(
+                &mut GliumRenderer::new(
+                    window,
+                    &mut target,
+                    &mut g2d,
+                    &mut tile_renderer,
+                    args.viewport(),
+                ),
+                alpha,
+            );
 
             // swaps the back buffer with the front buffer consuming the frame
             target.finish().unwrap();
         } // end render event
 
-        if let Some(button) = event.press_args() {
-            if let Button::Keyboard(key) = button {
-                match key {
-                    Key::A | Key::Left => {
-                        let temp_pos = camera.get_x() - 16;
-                        if temp_pos > 0 {
-                            camera.pos_x(temp_pos);
-                        } else {
-                            camera.pos_x(0);
-                        }
-                    }
-                    Key::D | Key::Right => {
-                        let temp_pos = camera.get_x() + 16;
-                        if temp_pos < camera.get_x_max() {
-                            camera.pos_x(temp_pos);
-                        } else {
-                            camera.x_to_max()
-                        }
-                    }
-                    Key::W | Key::Up => {
-                        let temp_pos = camera.get_y() - 16;
-                        if temp_pos > 0 {
-                            camera.pos_y(temp_pos);
-                        } else {
-                            camera.pos_y(0);
-                        }
-                    }
-                    Key::S | Key::Down => {
-                        let temp_pos = camera.get_y() + 16;
-                        if temp_pos <= camera.get_y_max() {
-                            camera.pos_y(temp_pos);
-                        } else {
-                            camera.y_to_max();
-                        }
-                    }
-                    _ => (),
-                }
-            } else {
-                // this covers the mouse events that we will ignore
-                ()
+        if let Some(args) = event.button_args() {
+            // what the last frame cost, for profiling
+            if args.button == Button::Keyboard(Key::F3) {
+                println!("{:?}", tile_renderer.get_stats());
             }
+
+            stack.handle_input(args.button, args.state);
+        }
+
+        if let Some(args) = event.controller_axis_args() {
+            stack.handle_axis(args);
+        }
+
+        // keys let go of while the window is in the background never get
+        // released, so drop everything
+        if event.focus_args() == Some(false) {
+            stack.get_mut_game().get_mut_input().release_all();
         }
 
         if let Some(size) = event.resize_args() {
-            camera.resize(size[0] as i32, size[1] as i32);
+            stack
+                .get_mut_game()
+                .get_mut_camera()
+                .resize(size[0] as i32, size[1] as i32);
         }
 
-        if let Some(_) = event.close_args() {
-            // This is where save_game should probably be called
+        if stack.is_empty() {
+            break 'game_loop;
+        }
+
+        if event.close_args().is_some() {
             println!("Game window was closed. Exiting!");
             break 'game_loop;
         }
     }
+
+    let saved = slots.and_then(|slots| {
+        let save = SaveGame::capture(stack.get_game())?;
+        slots.save(AUTOSAVE, &save)
+    });
+    if let Err(error) = saved {
+        println!("Could not save the game: {}", error);
+    }
+
+    if let Some(path) = record {
+        let recording = stack.get_mut_game().get_mut_input().stop_recording();
+        match recording.unwrap_or_default().save(&path) {
+            Ok(()) => println!("Saved the recording to {:?}", path),
+            Err(error) => println!("Could not save the recording: {}", error),
+        }
+    }
 }
@@ -1,29 +1,50 @@
 //! The world map and others + tiles management
 
-extern crate find_folder;
-extern crate tiled;
-
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 
 // for the map
-use glium_graphics::{Flip, GliumWindow, ImageSize, Texture, TextureSettings};
-use tiled::parse_file;
+use animation::{parse_animations, TileAnimation};
+use chunk::{split, Chunk, CHUNK_SIZE};
+use collision::{CollisionGrid, Rect};
+use glium_graphics::Texture;
+use objects::MapObjects;
+use render::TextureLoader;
+use tiled::{parse_file, PropertyValue};
+
+// Tiled stores the flip/rotation flags in the top three bits of a gid
+pub const FLIP_FLAGS: u32 = 0xE000_0000;
 
-// Defines a map in the game
-pub struct Map {
+// Defines a map in the game, T is the texture type of the renderer drawing it
+pub struct Map<T = Texture> {
     // Tiled Info
     map: tiled::Map,
-    tile_set: tiled::Tileset,
     tile_width: i32,
     tile_height: i32,
-    tile_sheet: Texture,
 
-    // Dimensions
+    // One sheet per tileset, sorted by first gid
+    tile_sheets: Vec<TileSheet<T>>,
+
+    // Layers in the order they are drawn
+    layers: Vec<Layer>,
+
+    // Animated tiles keyed by gid, and how long they have been playing in seconds
+    animations: HashMap<u32, TileAnimation>,
+    clock: f64,
+
+    // Which tiles block movement
+    collision: CollisionGrid,
+
+    // Spawns, warps and the rest of the object layers
+    objects: MapObjects,
+
+    // Dimensions in tiles
     width: i32,
     height: i32,
 }
 
-impl Map {
+impl<T> Map<T> {
     // Getters
     pub fn get_width(&self) -> i32 {
         self.width
@@ -41,50 +62,694 @@ impl Map {
         self.tile_height
     }
 
+    /// Returns the width of the whole map in pixels
+    pub fn get_pixel_width(&self) -> i32 {
+        self.width * self.tile_width
+    }
+
+    /// Returns the height of the whole map in pixels
+    pub fn get_pixel_height(&self) -> i32 {
+        self.height * self.tile_height
+    }
+
     pub fn get_map(&self) -> &tiled::Map {
         &self.map
     }
 
-    pub fn get_tile_set(&self) -> &tiled::Tileset {
-        &self.tile_set
+    pub fn get_tile_sheets(&self) -> &[TileSheet<T>] {
+        &self.tile_sheets
+    }
+
+    /// Returns every tile layer in draw order, including hidden ones
+    pub fn get_layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Returns the visible layers that are drawn beneath the sprites
+    pub fn get_ground_layers(&self) -> impl Iterator<Item = &Layer> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.is_visible() && !layer.is_overhead() && !layer.is_collision())
+    }
+
+    /// Returns the visible layers that are drawn on top of the sprites
+    pub fn get_overhead_layers(&self) -> impl Iterator<Item = &Layer> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.is_visible() && layer.is_overhead() && !layer.is_collision())
+    }
+
+    pub fn get_collision(&self) -> &CollisionGrid {
+        &self.collision
+    }
+
+    /// Checks if the player can stand on a tile
+    pub fn is_walkable(&self, tile_x: i32, tile_y: i32) -> bool {
+        !self.collision.is_solid(tile_x, tile_y)
+    }
+
+    /// Moves a box in pixels by dx, dy and returns how far it can actually
+    /// go before it runs into something solid
+    pub fn sweep(&self, rect: &Rect, dx: f64, dy: f64) -> (f64, f64) {
+        self.collision.sweep(rect, dx, dy)
+    }
+
+    pub fn get_objects(&self) -> &MapObjects {
+        &self.objects
     }
 
-    pub fn get_tile_sheet(&self) -> &Texture {
-        &self.tile_sheet
+    pub fn get_animations(&self) -> &HashMap<u32, TileAnimation> {
+        &self.animations
+    }
+
+    pub fn get_clock(&self) -> f64 {
+        self.clock
+    }
+
+    /// Estimates how much memory the tileset textures take up, in bytes
+    pub fn get_texture_bytes(&self) -> usize {
+        self.tile_sheets
+            .iter()
+            .map(|sheet| (sheet.get_width().max(0) * sheet.get_height().max(0)) as usize * 4)
+            .sum()
+    }
+
+    /// Returns the gid that should be drawn in place of gid right now. This
+    /// is the current frame for animated tiles and gid itself for the rest
+    pub fn animate(&self, gid: u32) -> u32 {
+        let flags = gid & FLIP_FLAGS;
+
+        match self.animations.get(&(gid & !FLIP_FLAGS)) {
+            Some(animation) => match animation.frame_at((self.clock * 1000.0) as u64) {
+                Some(frame) => frame | flags,
+                None => gid,
+            },
+            None => gid,
+        }
+    }
+
+    /// Finds the sheet a global tile id belongs to along with the id of the
+    /// tile local to that sheet. Returns None for empty tiles
+    pub fn resolve_gid(&self, gid: u32) -> Option<(&TileSheet<T>, u32)> {
+        self.resolve_sheet(gid)
+            .map(|(index, local)| (&self.tile_sheets[index], local))
+    }
+
+    /// Like resolve_gid but gives the index of the sheet in get_tile_sheets
+    pub fn resolve_sheet(&self, gid: u32) -> Option<(usize, u32)> {
+        let first_gids: Vec<u32> = self
+            .tile_sheets
+            .iter()
+            .map(|sheet| sheet.get_first_gid())
+            .collect();
+
+        resolve_gid(&first_gids, gid)
     }
 }
 
-impl Map {
-    pub fn load(path: PathBuf, window: &mut GliumWindow) -> Map {
-        let map = match parse_file(&path) {
+impl<T> Map<T> {
+    /// Loads a map and the textures of its tilesets with the given loader,
+    /// panicking if anything goes wrong
+    pub fn load<L>(path: PathBuf, loader: &mut L) -> Map<T>
+    where
+        L: TextureLoader<Texture = T> + ?Sized,
+    {
+        match Map::open(&path, loader) {
             Ok(map) => map,
             Err(error) => panic!("{:?}", error),
+        }
+    }
+
+    /// Loads a map and the textures of its tilesets with the given loader
+    pub fn open<L>(path: &Path, loader: &mut L) -> Result<Map<T>, String>
+    where
+        L: TextureLoader<Texture = T> + ?Sized,
+    {
+        let map = parse_file(path).map_err(|error| format!("{:?}: {}", path, error))?;
+
+        let directory = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut tile_sheets = map
+            .tilesets
+            .iter()
+            .map(|tile_set| TileSheet::load(tile_set.clone(), directory, loader))
+            .collect::<Result<Vec<TileSheet<T>>, String>>()?;
+        tile_sheets.sort_by_key(|sheet| sheet.get_first_gid());
+
+        // tiled keeps the layers in file order, which is also the draw order
+        let layers: Vec<Layer> = map.layers.iter().map(Layer::from_tiled).collect();
+
+        let collision = CollisionGrid::from_layers(
+            &layers,
+            &CollisionGrid::solid_gids(&map.tilesets),
+            map.width as i32,
+            map.height as i32,
+            map.tile_width as i32,
+            map.tile_height as i32,
+        );
+
+        let objects = MapObjects::from_tiled(&map.object_groups)?;
+
+        // tiled parses the animation frames but keeps them private, so they
+        // are read separately
+        let animations = File::open(path)
+            .map_err(|error| error.to_string())
+            .and_then(|file| parse_animations(file, directory))?;
+
+        Ok(Map {
+            width: map.width as i32,
+            height: map.height as i32,
+            tile_width: map.tile_width as i32,
+            tile_height: map.tile_height as i32,
+            map,
+            tile_sheets,
+            layers,
+            animations,
+            clock: 0.0,
+            collision,
+            objects,
+        })
+    }
+}
+
+impl<T> Map<T> {
+    /// Advances the animation clock by dt seconds
+    pub fn update(&mut self, dt: f64) {
+        self.clock += dt;
+    }
+
+    /// Changes a tile on one of the layers. Returns false if there is no
+    /// such layer or the position is outside of it
+    pub fn set_tile(&mut self, layer: usize, x: usize, y: usize, gid: u32) -> bool {
+        let changed = match self.layers.get_mut(layer) {
+            Some(layer) => layer.set_tile(x, y, gid),
+            None => false,
         };
 
-        let tile_set = map.get_tileset_by_gid(1).unwrap().clone();
+        // the tile may have started or stopped blocking the way
+        if changed {
+            let solid_gids = CollisionGrid::solid_gids(&self.map.tilesets);
+            self.collision.update_tile(&self.layers, &solid_gids, x, y);
+        }
+        changed
+    }
+}
+
+/// Finds which of the tilesets, given by their first gids in ascending
+/// order, a global tile id belongs to. Returns the index of the tileset and
+/// the id local to it, or None if the tile is empty
+pub fn resolve_gid(first_gids: &[u32], gid: u32) -> Option<(usize, u32)> {
+    let gid = gid & !FLIP_FLAGS;
+
+    if gid == 0 {
+        return None;
+    }
+
+    first_gids
+        .iter()
+        .rposition(|&first_gid| first_gid <= gid)
+        .map(|index| (index, gid - first_gids[index]))
+}
+
+/// A tileset and the texture its tiles are drawn from
+pub struct TileSheet<T = Texture> {
+    tile_set: tiled::Tileset,
+    atlas: TilesetAtlas,
+    texture: T,
+
+    // Dimensions of the image
+    width: i32,
+    height: i32,
+}
+
+impl<T> TileSheet<T> {
+    // Getters
+    pub fn get_first_gid(&self) -> u32 {
+        self.tile_set.first_gid
+    }
+
+    pub fn get_tile_set(&self) -> &tiled::Tileset {
+        &self.tile_set
+    }
+
+    pub fn get_atlas(&self) -> &TilesetAtlas {
+        &self.atlas
+    }
+
+    pub fn get_texture(&self) -> &T {
+        &self.texture
+    }
+
+    pub fn get_width(&self) -> i32 {
+        self.width
+    }
 
-        let tile_sheet = path.parent().unwrap().join(&tile_set.images[0].source);
+    pub fn get_height(&self) -> i32 {
+        self.height
+    }
 
-        println!("{:?}", &tile_sheet);
+    pub fn get_tile_width(&self) -> i32 {
+        self.tile_set.tile_width as i32
+    }
 
-        let tile_sheet =
-            Texture::from_path(window, tile_sheet, Flip::None, &TextureSettings::new()).unwrap();
+    pub fn get_tile_height(&self) -> i32 {
+        self.tile_set.tile_height as i32
+    }
+}
 
-        let tile_width = tile_set.tile_width as i32;
-        let tile_height = tile_set.tile_height as i32;
+impl<T> TileSheet<T> {
+    /// Loads the texture of a tileset. The image is looked up relative to
+    /// the directory the map was loaded from
+    pub fn load<L>(
+        tile_set: tiled::Tileset,
+        directory: &Path,
+        loader: &mut L,
+    ) -> Result<TileSheet<T>, String>
+    where
+        L: TextureLoader<Texture = T> + ?Sized,
+    {
+        let (image, width, height) = match tile_set.images.first() {
+            Some(image) => (directory.join(&image.source), image.width, image.height),
+            None => return Err(format!("tileset {} has no image", tile_set.name)),
+        };
 
-        let (width, height) = tile_sheet.get_size();
-        let width = width as i32;
-        let height = height as i32;
+        let texture = loader.load_texture(&image)?;
 
-        Map {
+        Ok(TileSheet {
+            atlas: TilesetAtlas::new(&tile_set),
+            tile_set,
+            texture,
             width,
             height,
-            tile_width,
-            tile_height,
-            map,
-            tile_set,
-            tile_sheet,
+        })
+    }
+}
+
+/// Knows where each tile of a tileset sits in its image
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TilesetAtlas {
+    tile_width: u32,
+    tile_height: u32,
+    // pixels around the edge of the image
+    margin: u32,
+    // pixels between neighbouring tiles
+    spacing: u32,
+    columns: u32,
+    tile_count: u32,
+}
+
+impl TilesetAtlas {
+    // Getters
+    pub fn get_tile_width(&self) -> u32 {
+        self.tile_width
+    }
+
+    pub fn get_tile_height(&self) -> u32 {
+        self.tile_height
+    }
+
+    pub fn get_margin(&self) -> u32 {
+        self.margin
+    }
+
+    pub fn get_spacing(&self) -> u32 {
+        self.spacing
+    }
+
+    pub fn get_columns(&self) -> u32 {
+        self.columns
+    }
+
+    pub fn get_tile_count(&self) -> u32 {
+        self.tile_count
+    }
+}
+
+impl TilesetAtlas {
+    /// Builds the atlas of a tileset. The columns and tile count are worked
+    /// out from the image size the same way Tiled does, so only whole tiles
+    /// are counted
+    pub fn new(tile_set: &tiled::Tileset) -> TilesetAtlas {
+        let (image_width, image_height) = match tile_set.images.first() {
+            Some(image) => (image.width.max(0) as u32, image.height.max(0) as u32),
+            None => (0, 0),
+        };
+
+        let fit = |length: u32, tile: u32| -> u32 {
+            let usable = (length + tile_set.spacing).saturating_sub(2 * tile_set.margin);
+            usable / (tile + tile_set.spacing).max(1)
+        };
+
+        let columns = fit(image_width, tile_set.tile_width);
+        let rows = fit(image_height, tile_set.tile_height);
+
+        TilesetAtlas {
+            tile_width: tile_set.tile_width,
+            tile_height: tile_set.tile_height,
+            margin: tile_set.margin,
+            spacing: tile_set.spacing,
+            columns,
+            tile_count: columns * rows,
+        }
+    }
+
+    /// Returns the [x, y, width, height] rectangle of a tile in the image,
+    /// or None if the id is past the end of the tileset
+    pub fn src_rect(&self, local_id: u32) -> Option<[f64; 4]> {
+        if local_id >= self.tile_count {
+            return None;
+        }
+
+        let column = local_id % self.columns;
+        let row = local_id / self.columns;
+
+        Some([
+            (self.margin + column * (self.tile_width + self.spacing)) as f64,
+            (self.margin + row * (self.tile_height + self.spacing)) as f64,
+            self.tile_width as f64,
+            self.tile_height as f64,
+        ])
+    }
+}
+
+/// A tile layer of the map and the settings used to draw it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    name: String,
+    // size in tiles
+    width: usize,
+    height: usize,
+    // the tiles split into blocks, row by row
+    chunks: Vec<Chunk>,
+    chunk_size: usize,
+    opacity: f32,
+    visible: bool,
+    // drawn after the sprites so it covers them, ie tree tops and roofs
+    overhead: bool,
+}
+
+impl Layer {
+    // Getters
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    pub fn get_chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Returns rows of global tile ids, 0 means there is no tile
+    pub fn get_tiles(&self) -> Vec<Vec<u32>> {
+        (0..self.height)
+            .map(|y| (0..self.width).map(|x| self.get_tile(x, y)).collect())
+            .collect()
+    }
+
+    /// Returns the tile at a position in tiles, or 0 outside of the layer
+    pub fn get_tile(&self, x: usize, y: usize) -> u32 {
+        match self.chunk_index(x, y) {
+            Some(index) => self.chunks[index].get_tile(x, y),
+            None => 0,
+        }
+    }
+
+    /// Iterates over every tile as (x, y, gid), a chunk at a time
+    pub fn iter_tiles(&self) -> impl Iterator<Item = (usize, usize, u32)> + '_ {
+        self.chunks.iter().flat_map(|chunk| chunk.get_tiles())
+    }
+
+    /// Finds the chunks overlapping a rectangle in pixels, ie
+    /// Camera::get_rect. Only the chunks in range are looked at, so this
+    /// does not get slower as the map gets bigger
+    pub fn get_visible_chunks(
+        &self,
+        rect: (i32, i32, i32, i32),
+        tile_width: i32,
+        tile_height: i32,
+    ) -> impl Iterator<Item = &Chunk> + '_ {
+        let (x, y, width, height) = rect;
+        let chunk_width = tile_width * self.chunk_size as i32;
+        let chunk_height = tile_height * self.chunk_size as i32;
+        let chunks_wide = self.chunks_wide() as i32;
+        let chunks_high = (self.chunks.len() as i32)
+            .checked_div(chunks_wide)
+            .unwrap_or(0);
+
+        // the range of chunks touched by the rectangle, clamped to the layer
+        let first_x = x.div_euclid(chunk_width).max(0);
+        let first_y = y.div_euclid(chunk_height).max(0);
+        let last_x = (x + width - 1).div_euclid(chunk_width).min(chunks_wide - 1);
+        let last_y = (y + height - 1)
+            .div_euclid(chunk_height)
+            .min(chunks_high - 1);
+
+        let area = Rect::new(x as f64, y as f64, width as f64, height as f64);
+
+        (first_y..=last_y)
+            .flat_map(move |chunk_y| {
+                (first_x..=last_x).map(move |chunk_x| (chunk_y * chunks_wide + chunk_x) as usize)
+            })
+            .map(move |index| &self.chunks[index])
+            .filter(move |chunk| chunk.get_bounds(tile_width, tile_height).intersects(&area))
+    }
+
+    pub fn get_opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn is_overhead(&self) -> bool {
+        self.overhead
+    }
+
+    /// Collision layers mark solid tiles and are never drawn
+    pub fn is_collision(&self) -> bool {
+        self.name.eq_ignore_ascii_case("collision")
+    }
+
+    /// How many chunks make up a row of the layer
+    fn chunks_wide(&self) -> usize {
+        self.width.div_ceil(self.chunk_size)
+    }
+
+    fn chunk_index(&self, x: usize, y: usize) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        Some((y / self.chunk_size) * self.chunks_wide() + x / self.chunk_size)
+    }
+}
+
+impl Layer {
+    /// Builds a layer from one parsed by tiled. A layer is drawn above the
+    /// sprites when it has an `overhead` property set to true
+    pub fn from_tiled(layer: &tiled::Layer) -> Layer {
+        Layer::with_chunk_size(layer, CHUNK_SIZE)
+    }
+
+    /// Like from_tiled but with chunks of chunk_size by chunk_size tiles
+    pub fn with_chunk_size(layer: &tiled::Layer, chunk_size: usize) -> Layer {
+        let chunk_size = chunk_size.max(1);
+
+        Layer {
+            name: layer.name.clone(),
+            width: layer.tiles.iter().map(|row| row.len()).max().unwrap_or(0),
+            height: layer.tiles.len(),
+            chunks: split(&layer.tiles, chunk_size),
+            chunk_size,
+            opacity: layer.opacity,
+            visible: layer.visible,
+            overhead: is_true(&layer.properties, "overhead"),
+        }
+    }
+
+    /// Changes a tile, only the chunk it is in has to be rebuilt. Returns
+    /// false if the position is outside of the layer
+    pub fn set_tile(&mut self, x: usize, y: usize, gid: u32) -> bool {
+        match self.chunk_index(x, y) {
+            Some(index) => self.chunks[index].set_tile(x, y, gid),
+            None => false,
+        }
+    }
+}
+
+/// Checks if a Tiled property is set to true, either as a bool or a string
+pub fn is_true(properties: &tiled::Properties, name: &str) -> bool {
+    match properties.get(name) {
+        Some(PropertyValue::BoolValue(value)) => *value,
+        Some(PropertyValue::StringValue(value)) => value == "true",
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::{self, File};
+    use std::io::Read;
+
+    use image::RgbaImage;
+    use render::SoftwareLoader;
+
+    const LAYERED_MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.0" orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16">
+ <layer name="Ground" width="2" height="2">
+  <data encoding="csv">
+1,1,
+1,1
+</data>
+ </layer>
+ <layer name="Hidden" width="2" height="2" visible="0" opacity="0.5">
+  <data encoding="csv">
+2,0,
+0,2
+</data>
+ </layer>
+ <layer name="Roofs" width="2" height="2">
+  <properties>
+   <property name="overhead" type="bool" value="true"/>
+  </properties>
+  <data encoding="csv">
+0,3,
+3,0
+</data>
+ </layer>
+</map>"#;
+
+    #[test]
+    fn editing_the_collision_layer_changes_what_is_walkable() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/maps/town.tmx");
+        let mut map: Map<RgbaImage> = Map::open(&path, &mut SoftwareLoader).unwrap();
+        assert!(map.get_layers()[1].is_collision());
+
+        assert!(map.is_walkable(2, 3));
+        assert!(map.set_tile(1, 2, 3, 1));
+        assert!(!map.is_walkable(2, 3));
+        assert!(map.set_tile(1, 2, 3, 0));
+        assert!(map.is_walkable(2, 3));
+    }
+
+    #[test]
+    fn layers_keep_their_draw_settings() {
+        let map = tiled::parse(LAYERED_MAP.as_bytes()).unwrap();
+        let layers: Vec<Layer> = map.layers.iter().map(Layer::from_tiled).collect();
+
+        let names: Vec<&str> = layers.iter().map(|layer| layer.get_name()).collect();
+        assert_eq!(names, vec!["Ground", "Hidden", "Roofs"]);
+
+        assert!(layers[0].is_visible() && !layers[0].is_overhead());
+        assert!(!layers[1].is_visible());
+        assert_eq!(layers[1].get_opacity(), 0.5);
+        assert!(layers[2].is_overhead());
+        assert_eq!(layers[2].get_tiles(), vec![vec![0, 3], vec![3, 0]]);
+    }
+
+    #[test]
+    fn gids_resolve_to_their_tileset() {
+        let first_gids = [1, 1000];
+
+        assert_eq!(resolve_gid(&first_gids, 0), None);
+        assert_eq!(resolve_gid(&first_gids, 1), Some((0, 0)));
+        assert_eq!(resolve_gid(&first_gids, 999), Some((0, 998)));
+        assert_eq!(resolve_gid(&first_gids, 1000), Some((1, 0)));
+        assert_eq!(resolve_gid(&first_gids, 1042), Some((1, 42)));
+
+        // flipped tiles still come from the same place in the sheet
+        assert_eq!(resolve_gid(&first_gids, 1042 | 0x8000_0000), Some((1, 42)));
+    }
+
+    /// Pulls a numeric attribute out of the root element of a .tsx file
+    fn declared(source: &str, attribute: &str) -> u32 {
+        let start = source.find(&format!(" {}=\"", attribute)).unwrap() + attribute.len() + 3;
+        let end = start + source[start..].find('"').unwrap();
+        source[start..end].parse().unwrap()
+    }
+
+    #[test]
+    fn atlas_matches_every_tsx_in_assets() {
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let mut checked = 0;
+
+        for entry in fs::read_dir(&assets).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some("tsx".as_ref()) {
+                continue;
+            }
+
+            let mut source = String::new();
+            File::open(&path)
+                .unwrap()
+                .read_to_string(&mut source)
+                .unwrap();
+
+            let tile_set = tiled::parse_tileset(source.as_bytes(), 1).unwrap();
+            let atlas = TilesetAtlas::new(&tile_set);
+
+            let columns = declared(&source, "columns");
+            let tile_count = declared(&source, "tilecount");
+            assert_eq!(atlas.get_columns(), columns, "{:?}", path);
+            assert_eq!(atlas.get_tile_count(), tile_count, "{:?}", path);
+
+            let (width, height) = (tile_set.tile_width as f64, tile_set.tile_height as f64);
+            assert_eq!(atlas.src_rect(0), Some([0.0, 0.0, width, height]));
+            assert_eq!(
+                atlas.src_rect(columns + 1),
+                Some([width, height, width, height]),
+                "{:?}",
+                path
+            );
+
+            // the last tile sits in the bottom right corner of the image
+            let last = tile_count - 1;
+            assert_eq!(
+                atlas.src_rect(last),
+                Some([
+                    ((last % columns) * tile_set.tile_width) as f64,
+                    ((last / columns) * tile_set.tile_height) as f64,
+                    width,
+                    height,
+                ])
+            );
+            assert_eq!(atlas.src_rect(tile_count), None);
+
+            checked += 1;
         }
+
+        assert_eq!(checked, 4);
+    }
+
+    #[test]
+    fn atlas_skips_margin_and_spacing() {
+        let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tileset name="spaced" tilewidth="16" tileheight="16" spacing="2" margin="1" tilecount="12" columns="4">
+ <image source="spaced.png" width="72" height="54"/>
+</tileset>"#;
+
+        let tile_set = tiled::parse_tileset(source.as_bytes(), 1).unwrap();
+        let atlas = TilesetAtlas::new(&tile_set);
+
+        assert_eq!(atlas.get_columns(), 4);
+        assert_eq!(atlas.get_tile_count(), 12);
+        assert_eq!(atlas.src_rect(0), Some([1.0, 1.0, 16.0, 16.0]));
+        assert_eq!(atlas.src_rect(5), Some([19.0, 19.0, 16.0, 16.0]));
+        assert_eq!(atlas.src_rect(11), Some([55.0, 37.0, 16.0, 16.0]));
+        assert_eq!(atlas.src_rect(12), None);
     }
 }
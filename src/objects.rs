@@ -0,0 +1,346 @@
+//! Game entities placed on the object layers of a map in Tiled. The kind of
+//! entity comes from the object's type and its settings from custom properties
+
+use tiled::{Object, ObjectGroup, ObjectShape, PropertyValue};
+
+use collision::Rect;
+use map::is_true;
+
+/// A place the player can appear when entering the map
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spawn {
+    name: String,
+    x: f64,
+    y: f64,
+}
+
+impl Spawn {
+    // Getters
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+}
+
+/// Where an NPC starts out on the map
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpcSpawn {
+    name: String,
+    // which NPC to spawn, ie "runaway_dog"
+    npc: String,
+    x: f64,
+    y: f64,
+}
+
+impl NpcSpawn {
+    // Getters
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_npc(&self) -> &str {
+        &self.npc
+    }
+
+    pub fn get_position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+}
+
+/// An area that sends the player to a spawn on another map, ie a door
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warp {
+    area: Rect,
+    map: String,
+    spawn: String,
+}
+
+impl Warp {
+    // Getters
+    pub fn get_area(&self) -> &Rect {
+        &self.area
+    }
+
+    /// The name of the map the warp leads to
+    pub fn get_map(&self) -> &str {
+        &self.map
+    }
+
+    /// The name of the spawn the player appears at on the target map
+    pub fn get_spawn(&self) -> &str {
+        &self.spawn
+    }
+}
+
+/// An area that starts a scripted event when the player walks into it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trigger {
+    area: Rect,
+    event: String,
+    // only fire the first time the player enters
+    once: bool,
+}
+
+impl Trigger {
+    // Getters
+    pub fn get_area(&self) -> &Rect {
+        &self.area
+    }
+
+    pub fn get_event(&self) -> &str {
+        &self.event
+    }
+
+    pub fn is_once(&self) -> bool {
+        self.once
+    }
+}
+
+/// Something the player can read by facing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sign {
+    area: Rect,
+    text: String,
+}
+
+impl Sign {
+    // Getters
+    pub fn get_area(&self) -> &Rect {
+        &self.area
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Every entity placed on the object layers of a map
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MapObjects {
+    spawns: Vec<Spawn>,
+    npcs: Vec<NpcSpawn>,
+    warps: Vec<Warp>,
+    triggers: Vec<Trigger>,
+    signs: Vec<Sign>,
+}
+
+impl MapObjects {
+    /// Reads the entities out of a map's object groups. Objects without a
+    /// type or with a type the game does not know are skipped, so layers can
+    /// still hold notes and editor-only objects for the level designers.
+    /// Missing properties are errors
+    pub fn from_tiled(groups: &[ObjectGroup]) -> Result<MapObjects, String> {
+        let mut objects = MapObjects::default();
+
+        for object in groups.iter().flat_map(|group| group.objects.iter()) {
+            objects.add(object)?;
+        }
+
+        Ok(objects)
+    }
+
+    fn add(&mut self, object: &Object) -> Result<(), String> {
+        let (x, y) = (object.x as f64, object.y as f64);
+
+        match object.obj_type.to_lowercase().as_str() {
+            "spawn" => self.spawns.push(Spawn {
+                name: object.name.clone(),
+                x,
+                y,
+            }),
+            "npc" => self.npcs.push(NpcSpawn {
+                name: object.name.clone(),
+                npc: required(object, "npc")?,
+                x,
+                y,
+            }),
+            "warp" => self.warps.push(Warp {
+                area: area(object)?,
+                map: required(object, "map")?,
+                spawn: required(object, "spawn")?,
+            }),
+            "trigger" => self.triggers.push(Trigger {
+                area: area(object)?,
+                event: required(object, "event")?,
+                once: is_true(&object.properties, "once"),
+            }),
+            "sign" => self.signs.push(Sign {
+                area: area(object)?,
+                text: required(object, "text")?,
+            }),
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+impl MapObjects {
+    // Getters
+    pub fn get_spawns(&self) -> &[Spawn] {
+        &self.spawns
+    }
+
+    pub fn get_npcs(&self) -> &[NpcSpawn] {
+        &self.npcs
+    }
+
+    pub fn get_warps(&self) -> &[Warp] {
+        &self.warps
+    }
+
+    pub fn get_triggers(&self) -> &[Trigger] {
+        &self.triggers
+    }
+
+    pub fn get_signs(&self) -> &[Sign] {
+        &self.signs
+    }
+
+    /// Finds a spawn by name. An empty name picks the first spawn on the map
+    pub fn get_spawn(&self, name: &str) -> Option<&Spawn> {
+        if name.is_empty() {
+            return self.spawns.first();
+        }
+
+        self.spawns.iter().find(|spawn| spawn.name == name)
+    }
+
+    /// Finds the warp covering a point in pixels
+    pub fn warp_at(&self, x: f64, y: f64) -> Option<&Warp> {
+        self.warps.iter().find(|warp| warp.area.contains(x, y))
+    }
+
+    /// Finds every trigger covering a point in pixels
+    pub fn triggers_at(&self, x: f64, y: f64) -> impl Iterator<Item = &Trigger> {
+        self.triggers
+            .iter()
+            .filter(move |trigger| trigger.area.contains(x, y))
+    }
+
+    /// Finds the sign covering a point in pixels
+    pub fn sign_at(&self, x: f64, y: f64) -> Option<&Sign> {
+        self.signs.iter().find(|sign| sign.area.contains(x, y))
+    }
+}
+
+/// The area an object covers in pixels, only rectangles are supported
+fn area(object: &Object) -> Result<Rect, String> {
+    match object.shape {
+        ObjectShape::Rect { width, height } => Ok(Rect::new(
+            object.x as f64,
+            object.y as f64,
+            width as f64,
+            height as f64,
+        )),
+        _ => Err(format!(
+            "{}: only rectangles are supported",
+            describe(object)
+        )),
+    }
+}
+
+/// Reads a property that has to be set for the object to make sense
+fn required(object: &Object, name: &str) -> Result<String, String> {
+    match object.properties.get(name) {
+        Some(PropertyValue::StringValue(value)) => Ok(value.clone()),
+        Some(PropertyValue::IntValue(value)) => Ok(value.to_string()),
+        Some(_) => Err(format!("{}: {} should be a string", describe(object), name)),
+        None => Err(format!(
+            "{}: missing the {} property",
+            describe(object),
+            name
+        )),
+    }
+}
+
+/// Names an object for error messages
+fn describe(object: &Object) -> String {
+    format!("object {} {:?}", object.id, object.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OBJECT_MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.0" orientation="orthogonal" width="10" height="10" tilewidth="16" tileheight="16">
+ <objectgroup name="Sprites">
+  <object id="1" name="start" type="spawn" x="32" y="48"/>
+  <object id="2" name="front_door" type="spawn" x="80" y="16"/>
+  <object id="3" name="dog" type="npc" x="64" y="64">
+   <properties>
+    <property name="npc" value="runaway_dog"/>
+   </properties>
+  </object>
+ </objectgroup>
+ <objectgroup name="Events">
+  <object id="4" type="warp" x="80" y="0" width="16" height="16">
+   <properties>
+    <property name="map" value="house.tmx"/>
+    <property name="spawn" value="entrance"/>
+   </properties>
+  </object>
+  <object id="5" type="Trigger" x="0" y="96" width="160" height="32">
+   <properties>
+    <property name="event" value="meteor_crash"/>
+    <property name="once" type="bool" value="true"/>
+   </properties>
+  </object>
+  <object id="6" type="sign" x="112" y="48" width="16" height="16">
+   <properties>
+    <property name="text" value="Welcome to Onett"/>
+   </properties>
+  </object>
+  <object id="7" name="just a note" x="0" y="0" width="8" height="8"/>
+  <object id="8" name="camera guide" type="editor_guide" x="0" y="0" width="160" height="160"/>
+ </objectgroup>
+</map>"#;
+
+    fn objects() -> MapObjects {
+        let map = tiled::parse(OBJECT_MAP.as_bytes()).unwrap();
+        MapObjects::from_tiled(&map.object_groups).unwrap()
+    }
+
+    #[test]
+    fn objects_become_entities() {
+        let objects = objects();
+
+        assert_eq!(objects.get_spawns().len(), 2);
+        assert_eq!(objects.get_spawn("").unwrap().get_name(), "start");
+        assert_eq!(
+            objects.get_spawn("front_door").unwrap().get_position(),
+            (80.0, 16.0)
+        );
+        assert_eq!(objects.get_spawn("back_door"), None);
+
+        assert_eq!(objects.get_npcs()[0].get_npc(), "runaway_dog");
+
+        let warp = objects.warp_at(85.0, 10.0).unwrap();
+        assert_eq!(warp.get_map(), "house.tmx");
+        assert_eq!(warp.get_spawn(), "entrance");
+        assert_eq!(objects.warp_at(96.0, 10.0), None);
+
+        let triggers: Vec<&Trigger> = objects.triggers_at(50.0, 100.0).collect();
+        assert_eq!(triggers.len(), 1);
+        assert_eq!(triggers[0].get_event(), "meteor_crash");
+        assert!(triggers[0].is_once());
+
+        assert_eq!(
+            objects.sign_at(120.0, 50.0).unwrap().get_text(),
+            "Welcome to Onett"
+        );
+    }
+
+    #[test]
+    fn missing_properties_are_errors() {
+        let source = OBJECT_MAP.replace(r#"<property name="spawn" value="entrance"/>"#, "");
+        let map = tiled::parse(source.as_bytes()).unwrap();
+
+        let error = MapObjects::from_tiled(&map.object_groups).unwrap_err();
+        assert!(error.contains("spawn"), "{}", error);
+    }
+}
@@ -0,0 +1,314 @@
+//! The character the player controls as they walk around the map
+
+use collision::CollisionGrid;
+
+/// The directions a character can face and walk in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    Down,
+    Up,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// How far one step in this direction moves, in tiles
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Down => (0, 1),
+            Direction::Up => (0, -1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+}
+
+/// Where each frame of a character's walk cycle sits in a sprite sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteSheet {
+    // top left corner of the character in the sheet
+    origin: [u32; 2],
+    frame_width: u32,
+    frame_height: u32,
+    // the row used for each direction, in the order of Direction
+    rows: [u32; 4],
+    // the columns shown while walking, the first one is also used standing
+    cycle: Vec<u32>,
+}
+
+impl SpriteSheet {
+    /// Creates a sheet layout. Frames are laid out in a grid from origin,
+    /// with one row per direction
+    pub fn new(
+        origin: [u32; 2],
+        frame_width: u32,
+        frame_height: u32,
+        rows: [u32; 4],
+        cycle: Vec<u32>,
+    ) -> SpriteSheet {
+        SpriteSheet {
+            origin,
+            frame_width,
+            frame_height,
+            rows,
+            cycle,
+        }
+    }
+
+    /// The layout of assets/char_sample.png, which holds two characters side
+    /// by side. Each has three 16x32 frames per direction with the standing
+    /// frame in the middle
+    pub fn char_sample(character: u32) -> SpriteSheet {
+        SpriteSheet::new([character * 48, 0], 16, 32, [0, 1, 2, 3], vec![1, 0, 1, 2])
+    }
+
+    // Getters
+    pub fn get_frame_width(&self) -> u32 {
+        self.frame_width
+    }
+
+    pub fn get_frame_height(&self) -> u32 {
+        self.frame_height
+    }
+
+    /// Returns how many frames the walk cycle has
+    pub fn get_cycle_length(&self) -> usize {
+        self.cycle.len()
+    }
+
+    /// Returns the [x, y, width, height] rectangle of a frame of the walk cycle
+    pub fn src_rect(&self, direction: Direction, frame: usize) -> [f64; 4] {
+        let column = self.cycle[frame % self.cycle.len()];
+        let row = self.rows[direction as usize];
+
+        [
+            (self.origin[0] + column * self.frame_width) as f64,
+            (self.origin[1] + row * self.frame_height) as f64,
+            self.frame_width as f64,
+            self.frame_height as f64,
+        ]
+    }
+}
+
+/// A step from one tile to the next that is being walked
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Step {
+    from: (i32, i32),
+    // goes from 0 at the start of the step to 1 at the end
+    progress: f64,
+}
+
+/// The character the player walks around with. It always stands on a tile
+/// and slides smoothly between tiles when it walks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Player {
+    tile_x: i32,
+    tile_y: i32,
+    facing: Direction,
+    step: Option<Step>,
+    // tiles walked per second
+    speed: f64,
+    // how long the walk cycle has been playing, in seconds
+    walk_time: f64,
+    // how many frames of the walk cycle are shown per second
+    frame_rate: f64,
+}
+
+impl Player {
+    /// Creates a player standing on a tile, facing the screen
+    pub fn new(tile_x: i32, tile_y: i32) -> Player {
+        Player {
+            tile_x,
+            tile_y,
+            facing: Direction::Down,
+            step: None,
+            speed: 4.0,
+            walk_time: 0.0,
+            frame_rate: 8.0,
+        }
+    }
+
+    // Getters
+    pub fn get_tile(&self) -> (i32, i32) {
+        (self.tile_x, self.tile_y)
+    }
+
+    pub fn get_facing(&self) -> Direction {
+        self.facing
+    }
+
+    pub fn get_speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// Returns the tile the player is facing
+    pub fn get_facing_tile(&self) -> (i32, i32) {
+        let (dx, dy) = self.facing.offset();
+        (self.tile_x + dx, self.tile_y + dy)
+    }
+
+    /// Returns where the player is drawn in pixels, part way between tiles
+    /// while it is walking
+    pub fn get_position(&self, tile_width: i32, tile_height: i32) -> (f64, f64) {
+        let (x, y) = match self.step {
+            Some(step) => (
+                step.from.0 as f64 + (self.tile_x - step.from.0) as f64 * step.progress,
+                step.from.1 as f64 + (self.tile_y - step.from.1) as f64 * step.progress,
+            ),
+            None => (self.tile_x as f64, self.tile_y as f64),
+        };
+
+        (x * tile_width as f64, y * tile_height as f64)
+    }
+
+    /// Returns which frame of the walk cycle to draw
+    pub fn get_frame(&self) -> usize {
+        if self.is_moving() {
+            (self.walk_time * self.frame_rate) as usize
+        } else {
+            0
+        }
+    }
+}
+
+impl Player {
+    /// Sets how many tiles are walked per second
+    pub fn speed(&mut self, tiles_per_second: f64) {
+        self.speed = tiles_per_second;
+    }
+
+    /// Puts the player on a tile, cancelling any step being taken
+    pub fn place(&mut self, tile_x: i32, tile_y: i32) {
+        self.tile_x = tile_x;
+        self.tile_y = tile_y;
+        self.step = None;
+    }
+
+    /// Turns the player without walking
+    pub fn face(&mut self, direction: Direction) {
+        self.facing = direction;
+    }
+
+    /// Turns the player and starts a step onto the next tile if it can be
+    /// walked on. Does nothing while a step is already being taken. Returns
+    /// whether a step was started
+    pub fn walk(&mut self, direction: Direction, collision: &CollisionGrid) -> bool {
+        if self.is_moving() {
+            return false;
+        }
+
+        self.facing = direction;
+
+        let (x, y) = self.get_facing_tile();
+        if collision.is_solid(x, y) {
+            return false;
+        }
+
+        self.step = Some(Step {
+            from: (self.tile_x, self.tile_y),
+            progress: 0.0,
+        });
+        self.tile_x = x;
+        self.tile_y = y;
+
+        true
+    }
+
+    /// Advances the step being taken by dt seconds
+    pub fn update(&mut self, dt: f64) {
+        // the walk cycle starts over once the player has stood still for an
+        // update, so walking tile after tile keeps the cycle going
+        let finished = match self.step {
+            Some(ref mut step) => {
+                step.progress += self.speed * dt;
+                step.progress >= 1.0
+            }
+            None => {
+                self.walk_time = 0.0;
+                return;
+            }
+        };
+
+        self.walk_time += dt;
+
+        if finished {
+            self.step = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> CollisionGrid {
+        let mut grid = CollisionGrid::new(4, 4, 16, 16);
+        grid.set_solid(2, 1, true);
+        grid
+    }
+
+    #[test]
+    fn walking_slides_between_tiles() {
+        let mut player = Player::new(1, 1);
+
+        assert!(player.walk(Direction::Down, &grid()));
+        assert_eq!(player.get_tile(), (1, 2));
+        assert_eq!(player.get_position(16, 16), (16.0, 16.0));
+
+        player.update(0.125);
+        assert_eq!(player.get_position(16, 16), (16.0, 24.0));
+
+        // can not change course half way through a step
+        assert!(!player.walk(Direction::Left, &grid()));
+        assert_eq!(player.get_facing(), Direction::Down);
+
+        player.update(0.125);
+        assert!(!player.is_moving());
+        assert_eq!(player.get_position(16, 16), (16.0, 32.0));
+    }
+
+    #[test]
+    fn solid_tiles_turn_but_do_not_move() {
+        let mut player = Player::new(1, 1);
+
+        assert!(!player.walk(Direction::Right, &grid()));
+        assert_eq!(player.get_facing(), Direction::Right);
+        assert_eq!(player.get_tile(), (1, 1));
+
+        // the edge of the map
+        let mut player = Player::new(0, 0);
+        assert!(!player.walk(Direction::Up, &grid()));
+        assert_eq!(player.get_tile(), (0, 0));
+    }
+
+    #[test]
+    fn walk_cycle_follows_the_sprite_sheet() {
+        let sheet = SpriteSheet::char_sample(1);
+        let mut player = Player::new(1, 1);
+        player.speed(1.0);
+
+        // standing still shows the middle frame
+        assert_eq!(
+            sheet.src_rect(player.get_facing(), player.get_frame()),
+            [64.0, 0.0, 16.0, 32.0]
+        );
+
+        player.walk(Direction::Left, &grid());
+        player.update(0.25);
+        assert_eq!(player.get_frame(), 2);
+        assert_eq!(
+            sheet.src_rect(player.get_facing(), player.get_frame()),
+            [64.0, 64.0, 16.0, 32.0]
+        );
+
+        player.update(0.125);
+        assert_eq!(
+            sheet.src_rect(player.get_facing(), player.get_frame()),
+            [80.0, 64.0, 16.0, 32.0]
+        );
+    }
+}
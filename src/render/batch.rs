@@ -0,0 +1,283 @@
+//! Turns the visible chunks of a layer into vertices that can be drawn in a
+//! handful of calls instead of one call per tile
+
+use std::cell::Ref;
+use std::ops::Range;
+
+use camera::{Camera, Tile};
+use chunk::Chunk;
+use map::{Layer, Map, FLIP_FLAGS};
+
+/// A run of vertices in the camera's tile buffer that all come from the
+/// same tile sheet, so they can be drawn with a single call
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    // index of the sheet in Map::get_tile_sheets
+    sheet: usize,
+    vertices: Range<usize>,
+}
+
+impl Batch {
+    // Getters
+    pub fn get_sheet(&self) -> usize {
+        self.sheet
+    }
+
+    pub fn get_vertices(&self) -> Range<usize> {
+        self.vertices.clone()
+    }
+
+    /// Returns how many tiles are in the batch
+    pub fn get_tile_count(&self) -> usize {
+        self.vertices.len() / 6
+    }
+}
+
+/// Counts what went into drawing a frame, for profiling
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct RenderStats {
+    tiles: usize,
+    vertices: usize,
+    draw_calls: usize,
+}
+
+impl RenderStats {
+    // Getters
+    pub fn get_tiles(&self) -> usize {
+        self.tiles
+    }
+
+    pub fn get_vertices(&self) -> usize {
+        self.vertices
+    }
+
+    pub fn get_draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
+    /// Counts a batch that got drawn
+    pub fn record(&mut self, batch: &Batch) {
+        self.tiles += batch.get_tile_count();
+        self.vertices += batch.vertices.len();
+        self.draw_calls += 1;
+    }
+
+    /// Starts counting again, ie at the start of a frame
+    pub fn reset(&mut self) {
+        *self = RenderStats::default();
+    }
+}
+
+/// The vertices of a chunk, two triangles for every tile, grouped by the
+/// tile sheet they are drawn from. Positions are in map pixels so the mesh
+/// stays valid as the camera moves
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkMesh {
+    // indexed the same as Map::get_tile_sheets
+    sheets: Vec<Vec<Tile>>,
+    // the chunk revision and animation clock the mesh was built at
+    revision: u64,
+    clock: f64,
+    // animated tiles change as the clock moves, so the mesh does too
+    animated: bool,
+}
+
+impl ChunkMesh {
+    /// Builds the vertices for every tile of a chunk
+    pub fn build<T>(chunk: &Chunk, map: &Map<T>) -> ChunkMesh {
+        let mut sheets: Vec<Vec<Tile>> = vec![vec![]; map.get_tile_sheets().len()];
+        let mut animated = false;
+
+        for (x, y, tile) in chunk.get_tiles() {
+            animated |= map.get_animations().contains_key(&(tile & !FLIP_FLAGS));
+
+            if let Some((index, vertices)) = tile_vertices(map, x, y, map.animate(tile)) {
+                sheets[index].extend_from_slice(&vertices);
+            }
+        }
+
+        ChunkMesh {
+            sheets,
+            revision: chunk.get_revision(),
+            clock: map.get_clock(),
+            animated,
+        }
+    }
+
+    // Getters
+    /// Returns the vertices drawn from one tile sheet
+    pub fn get_vertices(&self, sheet: usize) -> &[Tile] {
+        self.sheets
+            .get(sheet)
+            .map_or(&[], |vertices| vertices.as_slice())
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.animated
+    }
+
+    /// Checks if the chunk has changed since the mesh was built
+    pub fn is_stale<T>(&self, chunk: &Chunk, map: &Map<T>) -> bool {
+        self.revision != chunk.get_revision() || (self.animated && self.clock != map.get_clock())
+    }
+}
+
+/// Fills the camera's tile buffer with the meshes of every chunk of the
+/// layer the camera can see, grouped by tile sheet. Meshes are cached on the
+/// chunks and only rebuilt when their tiles change
+pub fn batch_layer<T>(camera: &mut Camera, layer: &Layer, map: &Map<T>) -> Vec<Batch> {
+    let (tile_width, tile_height) = (map.get_tile_width(), map.get_tile_height());
+
+    let meshes: Vec<Ref<ChunkMesh>> = layer
+        .get_visible_chunks(camera.get_rect(), tile_width, tile_height)
+        .map(|chunk| chunk.get_mesh(map))
+        .collect();
+
+    camera.clear_tile_buffer();
+
+    // every sheet gets one run of the buffer so it can be drawn in one call
+    let mut batches = vec![];
+    for sheet in 0..map.get_tile_sheets().len() {
+        let start = camera.get_tile_buffer().len();
+        for mesh in &meshes {
+            camera
+                .get_mut_tile_buffer()
+                .extend_from_slice(mesh.get_vertices(sheet));
+        }
+
+        let end = camera.get_tile_buffer().len();
+        if end > start {
+            batches.push(Batch {
+                sheet,
+                vertices: start..end,
+            });
+        }
+    }
+
+    batches
+}
+
+/// The two triangles drawing a tile at (x, y) on the map, along with the
+/// index of the sheet they come from. None for empty tiles
+fn tile_vertices<T>(map: &Map<T>, x: usize, y: usize, gid: u32) -> Option<(usize, [Tile; 6])> {
+    let (index, tile) = map.resolve_sheet(gid)?;
+
+    let sheet = &map.get_tile_sheets()[index];
+    let src_rect = sheet.get_atlas().src_rect(tile)?;
+
+    let (left, top) = (
+        (x as i32 * map.get_tile_width()) as f32,
+        (y as i32 * map.get_tile_height()) as f32,
+    );
+    let (right, bottom) = (left + src_rect[2] as f32, top + src_rect[3] as f32);
+
+    // glium_graphics uploads textures upside down, so v is flipped the
+    // same way piston's own image drawing does it
+    let (width, height) = (sheet.get_width() as f64, sheet.get_height() as f64);
+    let u1 = (src_rect[0] / width) as f32;
+    let u2 = ((src_rect[0] + src_rect[2]) / width) as f32;
+    let v1 = (1.0 - src_rect[1] / height) as f32;
+    let v2 = (1.0 - (src_rect[1] + src_rect[3]) / height) as f32;
+
+    Some((
+        index,
+        [
+            Tile::new([left, top], [u1, v1]),
+            Tile::new([right, top], [u2, v1]),
+            Tile::new([left, bottom], [u1, v2]),
+            Tile::new([right, top], [u2, v1]),
+            Tile::new([right, bottom], [u2, v2]),
+            Tile::new([left, bottom], [u1, v2]),
+        ],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    use image::RgbaImage;
+    use render::SoftwareLoader;
+
+    fn map(name: &str) -> Map<RgbaImage> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(name);
+        Map::open(&path, &mut SoftwareLoader).unwrap()
+    }
+
+    fn layer(map: &Map<RgbaImage>, chunk_size: usize) -> Layer {
+        Layer::with_chunk_size(&map.get_map().layers[0], chunk_size)
+    }
+
+    #[test]
+    fn visible_chunks_become_one_batch_per_sheet() {
+        let map = map("tests/fixtures/maps/town.tmx");
+        let layer = layer(&map, 4);
+
+        // the view straddles the two chunks along the top of the map
+        let camera = &mut Camera::load(40, 0, 40, 24);
+        let batches = batch_layer(camera, &layer, &map);
+
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].get_sheet(), 0);
+        assert_eq!(batches[0].get_tile_count(), 32);
+        assert_eq!(camera.get_tile_buffer().len(), 192);
+
+        let mut stats = RenderStats::default();
+        stats.record(&batches[0]);
+        assert_eq!(
+            (
+                stats.get_tiles(),
+                stats.get_vertices(),
+                stats.get_draw_calls()
+            ),
+            (32, 192, 1)
+        );
+    }
+
+    #[test]
+    fn vertices_cover_the_tile_in_map_pixels() {
+        let map = map("tests/fixtures/maps/town.tmx");
+        let layer = layer(&map, 1);
+        let camera = &mut Camera::load(20, 20, 8, 8);
+
+        batch_layer(camera, &layer, &map);
+        let buffer = camera.get_tile_buffer();
+
+        // the camera sits inside the tile at (1, 1)
+        assert_eq!(buffer.len(), 6);
+        assert_eq!(buffer[0].get_position(), [16.0, 16.0]);
+        assert_eq!(buffer[4].get_position(), [32.0, 32.0]);
+
+        // the texture coordinates span exactly one tile of the sheet
+        let sheet = &map.get_tile_sheets()[0];
+        let [u1, v1] = buffer[0].get_tex_coords();
+        let [u2, v2] = buffer[4].get_tex_coords();
+        assert!(((u2 - u1) * sheet.get_width() as f32 - 16.0).abs() < 1e-3);
+        assert!(((v1 - v2) * sheet.get_height() as f32 - 16.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn meshes_are_rebuilt_only_when_tiles_change() {
+        let map = map("tests/fixtures/maps/town.tmx");
+        let mut layer = layer(&map, 4);
+
+        let first = layer.get_chunks()[0].get_mesh(&map).clone();
+        assert!(!first.is_stale(&layer.get_chunks()[0], &map));
+        assert_eq!(first.get_vertices(0).len(), 96);
+
+        // emptying a tile drops its triangles from the mesh
+        layer.set_tile(1, 1, 0);
+        assert!(first.is_stale(&layer.get_chunks()[0], &map));
+        assert_eq!(
+            layer.get_chunks()[0].get_mesh(&map).get_vertices(0).len(),
+            90
+        );
+
+        // other chunks are left alone
+        assert!(!layer.get_chunks()[1]
+            .get_mesh(&map)
+            .is_stale(&layer.get_chunks()[1], &map));
+    }
+}
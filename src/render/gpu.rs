@@ -0,0 +1,184 @@
+//! Draws whole layers of tiles straight through glium, uploading each layer
+//! as one vertex buffer instead of drawing every tile as its own image
+
+use glium::backend::Facade;
+use glium::index::{NoIndices, PrimitiveType};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::{Blend, DrawParameters, Frame, Program, Surface, VertexBuffer};
+use glium_graphics::{Glium2d, GliumWindow, Texture};
+use graphics::Viewport;
+
+use camera::Camera;
+use map::{Layer, Map};
+
+use super::batch::{batch_layer, RenderStats};
+use super::{Renderer, WindowRenderer};
+
+const VERTEX_SHADER: &str = include_str!("../../shaders/vertex_shader.glslv");
+const FRAGMENT_SHADER: &str = include_str!("../../shaders/fragment_shader.glslf");
+
+/// Draws map layers with the tile shaders in shaders/
+pub struct TileRenderer {
+    program: Program,
+    stats: RenderStats,
+}
+
+impl TileRenderer {
+    /// Compiles the tile shaders
+    pub fn new<F: Facade>(facade: &F) -> Result<TileRenderer, String> {
+        let program = Program::from_source(facade, VERTEX_SHADER, FRAGMENT_SHADER, None)
+            .map_err(|error| format!("{:?}", error))?;
+
+        Ok(TileRenderer {
+            program,
+            stats: RenderStats::default(),
+        })
+    }
+
+    // Getters
+    /// What has been drawn since the last reset_stats
+    pub fn get_stats(&self) -> &RenderStats {
+        &self.stats
+    }
+
+    /// Starts counting a new frame
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+
+    /// Draws the visible part of a layer, with one draw call for each tile
+    /// sheet the layer uses
+    pub fn draw_layer<F, S>(
+        &mut self,
+        facade: &F,
+        surface: &mut S,
+        camera: &mut Camera,
+        layer: &Layer,
+        map: &Map<Texture>,
+    ) -> Result<(), String>
+    where
+        F: Facade,
+        S: Surface,
+    {
+        let batches = batch_layer(camera, layer, map);
+        if batches.is_empty() {
+            return Ok(());
+        }
+
+        let vertices = VertexBuffer::new(facade, camera.get_tile_buffer())
+            .map_err(|error| format!("{:?}", error))?;
+
+        let transform = projection(camera);
+        let params = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        for batch in &batches {
+            let texture = &map.get_tile_sheets()[batch.get_sheet()].get_texture().0;
+
+            // nearest filtering keeps the pixel art sharp when scaled
+            let sampler = texture
+                .sampled()
+                .wrap_function(SamplerWrapFunction::Clamp)
+                .magnify_filter(MagnifySamplerFilter::Nearest)
+                .minify_filter(MinifySamplerFilter::Nearest);
+
+            let uniforms = uniform! {
+                transform: transform,
+                tile_sheet: sampler,
+                opacity: layer.get_opacity(),
+            };
+
+            let slice = vertices
+                .slice(batch.get_vertices())
+                .ok_or_else(|| "batch is outside of the vertex buffer".to_string())?;
+
+            surface
+                .draw(
+                    slice,
+                    NoIndices(PrimitiveType::TrianglesList),
+                    &self.program,
+                    &uniforms,
+                    &params,
+                )
+                .map_err(|error| format!("{:?}", error))?;
+
+            self.stats.record(batch);
+        }
+
+        Ok(())
+    }
+}
+
+/// Draws a frame of the game window. Tiles go through the TileRenderer a
+/// layer at a time and everything else through piston's Glium2d
+pub struct GliumRenderer<'a> {
+    window: &'a GliumWindow,
+    target: &'a mut Frame,
+    g2d: &'a mut Glium2d,
+    tiles: &'a mut TileRenderer,
+    viewport: Viewport,
+}
+
+impl<'a> GliumRenderer<'a> {
+    /// Wraps everything needed to draw a frame
+    pub fn new(
+        window: &'a GliumWindow,
+        target: &'a mut Frame,
+        g2d: &'a mut Glium2d,
+        tiles: &'a mut TileRenderer,
+        viewport: Viewport,
+    ) -> GliumRenderer<'a> {
+        GliumRenderer {
+            window,
+            target,
+            g2d,
+            tiles,
+            viewport,
+        }
+    }
+}
+
+impl<'a> Renderer for GliumRenderer<'a> {
+    type Texture = Texture;
+
+    fn clear(&mut self, color: [f32; 4]) {
+        self.target
+            .clear_color(color[0], color[1], color[2], color[3]);
+    }
+
+    fn draw_image(&mut self, texture: &Texture, src_rect: [f64; 4], pos: [f64; 2], opacity: f32) {
+        self.g2d
+            .draw(self.target, self.viewport, |context, graphics| {
+                WindowRenderer::new(context, graphics).draw_image(texture, src_rect, pos, opacity);
+            });
+    }
+
+    fn fill_rect(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        self.g2d
+            .draw(self.target, self.viewport, |context, graphics| {
+                WindowRenderer::new(context, graphics).fill_rect(color, rect);
+            });
+    }
+
+    fn draw_tiles(&mut self, layer: &Layer, map: &Map<Texture>, camera: &mut Camera) {
+        self.tiles
+            .draw_layer(self.window, self.target, camera, layer, map)
+            .expect("failed to draw tiles");
+    }
+}
+
+/// Maps pixels on the map to OpenGL's -1 to 1 screen space, with y pointing
+/// down like it does everywhere else in the game
+fn projection(camera: &Camera) -> [[f32; 4]; 4] {
+    let (x, y) = (camera.get_x() as f32, camera.get_y() as f32);
+    let (width, height) = (camera.get_width() as f32, camera.get_height() as f32);
+
+    [
+        [2.0 / width, 0.0, 0.0, 0.0],
+        [0.0, -2.0 / height, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [-1.0 - 2.0 * x / width, 1.0 + 2.0 * y / height, 0.0, 1.0],
+    ]
+}
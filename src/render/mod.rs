@@ -0,0 +1,143 @@
+//! Draws maps through a backend, so the same drawing code can target the
+//! game window or an image in memory
+
+use std::path::Path;
+
+use image::{self, RgbaImage};
+
+use camera::Camera;
+use map::{Layer, Map};
+
+mod batch;
+mod gpu;
+mod software;
+mod window;
+
+pub use self::batch::{batch_layer, Batch, ChunkMesh, RenderStats};
+pub use self::gpu::{GliumRenderer, TileRenderer};
+pub use self::software::{SoftwareLoader, SoftwareRenderer};
+pub use self::window::{GliumLoader, WindowRenderer};
+
+/// A target that tiles and sprites can be drawn onto
+pub trait Renderer {
+    /// The texture type images are drawn from
+    type Texture;
+
+    /// Fills the whole target with a colour
+    fn clear(&mut self, color: [f32; 4]);
+
+    /// Draws the src_rect ([x, y, width, height]) part of a texture with its
+    /// top left corner at pos, blended with the given opacity
+    fn draw_image(
+        &mut self,
+        texture: &Self::Texture,
+        src_rect: [f64; 4],
+        pos: [f64; 2],
+        opacity: f32,
+    );
+
+    /// Fills a rectangle ([x, y, width, height]) with a colour, blending it
+    /// by the colour's alpha
+    fn fill_rect(&mut self, color: [f32; 4], rect: [f64; 4]);
+
+    /// Draws the tiles of a layer the camera can see. Backends that can draw
+    /// a whole layer at once override this
+    fn draw_tiles(&mut self, layer: &Layer, map: &Map<Self::Texture>, camera: &mut Camera) {
+        draw_layer(self, layer, map, camera);
+    }
+}
+
+/// Turns image files into textures a renderer can draw
+pub trait TextureLoader {
+    /// The texture type that gets loaded
+    type Texture;
+
+    /// Loads the image at path
+    fn load_texture(&mut self, path: &Path) -> Result<Self::Texture, String>;
+
+    /// Turns an image already in memory into a texture
+    fn load_image(&mut self, image: &RgbaImage) -> Result<Self::Texture, String>;
+
+    /// Loads the image at path with every pixel of the key colour made
+    /// transparent, for sprite sheets drawn on a solid background
+    fn load_texture_keyed(&mut self, path: &Path, key: [u8; 3]) -> Result<Self::Texture, String> {
+        let mut image = match image::open(path) {
+            Ok(image) => image.to_rgba(),
+            Err(error) => return Err(format!("{:?}: {}", path, error)),
+        };
+
+        for pixel in image.pixels_mut() {
+            if pixel.data[..3] == key {
+                pixel.data = [0, 0, 0, 0];
+            }
+        }
+
+        self.load_image(&image)
+    }
+}
+
+/// Draws every visible layer of the map. The ground layers go first and the
+/// overhead layers last, so anything drawn between them should be drawn
+/// with draw_layer instead
+pub fn draw_map<R: Renderer + ?Sized>(renderer: &mut R, map: &Map<R::Texture>, camera: &Camera) {
+    for layer in map.get_ground_layers() {
+        draw_layer(renderer, layer, map, camera);
+    }
+
+    for layer in map.get_overhead_layers() {
+        draw_layer(renderer, layer, map, camera);
+    }
+}
+
+/// Draws the tiles of a single layer that fall inside of the camera
+pub fn draw_layer<R: Renderer + ?Sized>(
+    renderer: &mut R,
+    layer: &Layer,
+    map: &Map<R::Texture>,
+    camera: &Camera,
+) {
+    let (tile_width, tile_height) = (map.get_tile_width(), map.get_tile_height());
+
+    for (x, y, tile) in visible_tiles(layer, map, camera) {
+        // find the sheet the tile comes from, zero means there is no tile
+        let (sheet, tile) = match map.resolve_gid(map.animate(tile)) {
+            Some(found) => found,
+            None => continue,
+        };
+
+        // where the tile sits in the tilesheet
+        let src_rect = match sheet.get_atlas().src_rect(tile) {
+            Some(rect) => rect,
+            None => continue,
+        };
+
+        // Converts to the cartesian plane
+        let pos = [
+            (x as i32 * tile_width - camera.get_x()) as f64,
+            (y as i32 * tile_height - camera.get_y()) as f64,
+        ];
+
+        renderer.draw_image(sheet.get_texture(), src_rect, pos, layer.get_opacity());
+    }
+}
+
+/// Iterates over the tiles of a layer the camera can see, including partly
+/// visible ones, as (x, y, gid). Only the chunks under the camera are looked at
+pub fn visible_tiles<'a, T>(
+    layer: &'a Layer,
+    map: &Map<T>,
+    camera: &Camera,
+) -> impl Iterator<Item = (usize, usize, u32)> + 'a {
+    let (tile_width, tile_height) = (map.get_tile_width(), map.get_tile_height());
+
+    let first_x = camera.get_x().div_euclid(tile_width).max(0) as usize;
+    let first_y = camera.get_y().div_euclid(tile_height).max(0) as usize;
+    let last_x = ((camera.get_x() + camera.get_width()).div_euclid(tile_width) + 1).max(0) as usize;
+    let last_y =
+        ((camera.get_y() + camera.get_height()).div_euclid(tile_height) + 1).max(0) as usize;
+
+    layer
+        .get_visible_chunks(camera.get_rect(), tile_width, tile_height)
+        .flat_map(|chunk| chunk.get_tiles())
+        .filter(move |&(x, y, _)| first_x <= x && x < last_x && first_y <= y && y < last_y)
+}
@@ -0,0 +1,251 @@
+//! Draws into an image in memory, so frames can be rendered without a GPU
+
+use std::io;
+use std::path::Path;
+
+use image::{self, Rgba, RgbaImage};
+
+use super::{Renderer, TextureLoader};
+
+/// Renders into an RGBA image buffer
+pub struct SoftwareRenderer {
+    target: RgbaImage,
+}
+
+impl SoftwareRenderer {
+    /// Creates a renderer with a transparent target of the given size
+    pub fn new(width: u32, height: u32) -> SoftwareRenderer {
+        SoftwareRenderer {
+            target: RgbaImage::from_pixel(width, height, Rgba { data: [0, 0, 0, 0] }),
+        }
+    }
+
+    /// Returns the frame drawn so far
+    pub fn get_image(&self) -> &RgbaImage {
+        &self.target
+    }
+
+    /// Hands the frame over, consuming the renderer
+    pub fn into_image(self) -> RgbaImage {
+        self.target
+    }
+
+    /// Writes the frame to a file, the format is picked from the extension
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.target.save(path)
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    type Texture = RgbaImage;
+
+    fn clear(&mut self, color: [f32; 4]) {
+        let pixel = Rgba {
+            data: [
+                to_channel(color[0]),
+                to_channel(color[1]),
+                to_channel(color[2]),
+                to_channel(color[3]),
+            ],
+        };
+
+        for target in self.target.pixels_mut() {
+            *target = pixel;
+        }
+    }
+
+    fn draw_image(&mut self, texture: &RgbaImage, src_rect: [f64; 4], pos: [f64; 2], opacity: f32) {
+        let (src_x, src_y) = (src_rect[0] as i64, src_rect[1] as i64);
+        let (width, height) = (src_rect[2] as i64, src_rect[3] as i64);
+        let (dest_x, dest_y) = (pos[0].round() as i64, pos[1].round() as i64);
+
+        for y in 0..height {
+            for x in 0..width {
+                let (from_x, from_y) = (src_x + x, src_y + y);
+                let (to_x, to_y) = (dest_x + x, dest_y + y);
+
+                // anything outside of either image is clipped
+                if !contains(texture, from_x, from_y) || !contains(&self.target, to_x, to_y) {
+                    continue;
+                }
+
+                let source = texture.get_pixel(from_x as u32, from_y as u32);
+                let target = self.target.get_pixel_mut(to_x as u32, to_y as u32);
+                *target = blend(*source, *target, opacity);
+            }
+        }
+    }
+
+    fn fill_rect(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        let source = Rgba {
+            data: [
+                to_channel(color[0]),
+                to_channel(color[1]),
+                to_channel(color[2]),
+                to_channel(color[3]),
+            ],
+        };
+
+        let (left, top) = (rect[0].round() as i64, rect[1].round() as i64);
+        let (right, bottom) = (left + rect[2].round() as i64, top + rect[3].round() as i64);
+
+        for y in top.max(0)..bottom.min(self.target.height() as i64) {
+            for x in left.max(0)..right.min(self.target.width() as i64) {
+                let target = self.target.get_pixel_mut(x as u32, y as u32);
+                *target = blend(source, *target, 1.0);
+            }
+        }
+    }
+}
+
+/// Loads textures as RGBA image buffers for the SoftwareRenderer
+pub struct SoftwareLoader;
+
+impl TextureLoader for SoftwareLoader {
+    type Texture = RgbaImage;
+
+    fn load_texture(&mut self, path: &Path) -> Result<RgbaImage, String> {
+        match image::open(path) {
+            Ok(image) => Ok(image.to_rgba()),
+            Err(error) => Err(format!("{:?}: {}", path, error)),
+        }
+    }
+
+    fn load_image(&mut self, image: &RgbaImage) -> Result<RgbaImage, String> {
+        Ok(image.clone())
+    }
+}
+
+/// Checks that a pixel coordinate is inside of an image
+fn contains(image: &RgbaImage, x: i64, y: i64) -> bool {
+    x >= 0 && y >= 0 && x < image.width() as i64 && y < image.height() as i64
+}
+
+/// Converts a colour channel from 0.0 - 1.0 to 0 - 255
+fn to_channel(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Draws source over target, the same as alpha blending on the GPU
+fn blend(source: Rgba<u8>, target: Rgba<u8>, opacity: f32) -> Rgba<u8> {
+    let alpha = source.data[3] as f32 / 255.0 * opacity;
+    let target_alpha = target.data[3] as f32 / 255.0;
+    let out_alpha = alpha + target_alpha * (1.0 - alpha);
+
+    if out_alpha <= 0.0 {
+        return Rgba { data: [0, 0, 0, 0] };
+    }
+
+    let mix = |channel: usize| -> u8 {
+        let color = source.data[channel] as f32 * alpha
+            + target.data[channel] as f32 * target_alpha * (1.0 - alpha);
+        (color / out_alpha).round() as u8
+    };
+
+    Rgba {
+        data: [mix(0), mix(1), mix(2), to_channel(out_alpha)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::path::PathBuf;
+
+    use camera::Camera;
+    use map::Map;
+    use render::draw_map;
+
+    fn crate_path(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(path)
+    }
+
+    /// Renders a map from the assets folder at a camera position and checks
+    /// it against a golden image in tests/golden. Running the tests with
+    /// UPDATE_GOLDEN set rewrites the golden image instead
+    fn assert_golden(map_name: &str, camera: &Camera, golden: &str) {
+        let map = Map::load(crate_path("assets").join(map_name), &mut SoftwareLoader);
+
+        let mut renderer =
+            SoftwareRenderer::new(camera.get_width() as u32, camera.get_height() as u32);
+        renderer.clear([0.0, 0.0, 0.0, 1.0]);
+        draw_map(&mut renderer, &map, camera);
+
+        let golden = crate_path("tests/golden").join(golden);
+        if env::var_os("UPDATE_GOLDEN").is_some() {
+            renderer.save(&golden).unwrap();
+        }
+
+        let expected = image::open(&golden).unwrap().to_rgba();
+        let frame = renderer.get_image();
+        assert_eq!(frame.dimensions(), expected.dimensions());
+        assert!(
+            frame.iter().eq(expected.iter()),
+            "frame does not match {:?}",
+            golden
+        );
+    }
+
+    #[test]
+    fn map_matches_golden_image() {
+        assert_golden(
+            "rofl_map.tmx",
+            &Camera::load(0, 0, 320, 240),
+            "rofl_map_origin.png",
+        );
+    }
+
+    #[test]
+    fn scrolled_map_matches_golden_image() {
+        // not lined up with the tile grid so partly visible tiles get clipped
+        assert_golden(
+            "rofl_map.tmx",
+            &Camera::load(120, 88, 320, 240),
+            "rofl_map_scrolled.png",
+        );
+    }
+
+    #[test]
+    fn images_are_blended_with_opacity() {
+        let texture = RgbaImage::from_pixel(
+            2,
+            2,
+            Rgba {
+                data: [255, 0, 0, 255],
+            },
+        );
+
+        let mut renderer = SoftwareRenderer::new(4, 4);
+        renderer.clear([0.0, 0.0, 1.0, 1.0]);
+        renderer.draw_image(&texture, [0.0, 0.0, 2.0, 2.0], [3.0, 3.0], 0.5);
+
+        let image = renderer.into_image();
+        assert_eq!(image.get_pixel(2, 2).data, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(3, 3).data, [128, 0, 128, 255]);
+    }
+
+    #[test]
+    fn rectangles_are_clipped_and_blended() {
+        let mut renderer = SoftwareRenderer::new(4, 4);
+        renderer.clear([0.0, 0.0, 1.0, 1.0]);
+        renderer.fill_rect([1.0, 0.0, 0.0, 0.5], [2.0, -1.0, 10.0, 2.0]);
+
+        let image = renderer.into_image();
+        assert_eq!(image.get_pixel(1, 0).data, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(3, 0).data, [128, 0, 127, 255]);
+        assert_eq!(image.get_pixel(3, 1).data, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn key_colour_is_made_transparent() {
+        let texture = SoftwareLoader
+            .load_texture_keyed(&crate_path("assets/char_sample.png"), [0, 255, 0])
+            .unwrap();
+
+        // the corner of the sheet is background, the middle of a frame is not
+        assert_eq!(texture.get_pixel(0, 0).data, [0, 0, 0, 0]);
+        assert_eq!(texture.get_pixel(24, 16).data[3], 255);
+    }
+}
@@ -0,0 +1,97 @@
+//! Draws through piston's graphics backends, which is what the game window uses
+
+use std::path::Path;
+use std::rc::Rc;
+
+use glium::backend::Context as GliumContext;
+use glium_graphics::{Flip, GliumWindow, Texture, TextureSettings};
+use graphics::{self as piston_graphics, Context, DrawState, Graphics, Transformed};
+use image::RgbaImage;
+
+use super::{Renderer, TextureLoader};
+
+/// Renders with a piston graphics backend, ie the one given to Glium2d::draw
+pub struct WindowRenderer<'a, G: 'a> {
+    context: Context,
+    graphics: &'a mut G,
+}
+
+impl<'a, G: Graphics> WindowRenderer<'a, G> {
+    /// Wraps the context and graphics handed out for a frame
+    pub fn new(context: Context, graphics: &'a mut G) -> WindowRenderer<'a, G> {
+        WindowRenderer { context, graphics }
+    }
+}
+
+impl<'a, G: Graphics> Renderer for WindowRenderer<'a, G> {
+    type Texture = G::Texture;
+
+    fn clear(&mut self, color: [f32; 4]) {
+        piston_graphics::clear(color, self.graphics);
+    }
+
+    fn draw_image(
+        &mut self,
+        texture: &Self::Texture,
+        src_rect: [f64; 4],
+        pos: [f64; 2],
+        opacity: f32,
+    ) {
+        // the opacity is applied by tinting the image
+        piston_graphics::image::Image::new()
+            .color([1.0, 1.0, 1.0, opacity])
+            .src_rect(src_rect)
+            .draw(
+                texture,
+                &DrawState::default(),
+                self.context.transform.trans(pos[0], pos[1]),
+                self.graphics,
+            );
+    }
+
+    fn fill_rect(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        piston_graphics::rectangle(color, rect, self.context.transform, self.graphics);
+    }
+}
+
+/// Loads textures for the game window. Unlike the window itself it can be
+/// handed off and kept, ie by the Game
+#[derive(Clone)]
+pub struct GliumLoader {
+    context: Rc<GliumContext>,
+}
+
+impl GliumLoader {
+    /// Creates a loader sharing the window's OpenGL context
+    pub fn new(window: &GliumWindow) -> GliumLoader {
+        GliumLoader {
+            context: window.context.clone(),
+        }
+    }
+}
+
+impl TextureLoader for GliumLoader {
+    type Texture = Texture;
+
+    fn load_texture(&mut self, path: &Path) -> Result<Texture, String> {
+        Texture::from_path(&mut self.context, path, Flip::None, &TextureSettings::new())
+    }
+
+    fn load_image(&mut self, image: &RgbaImage) -> Result<Texture, String> {
+        Texture::from_image(&mut self.context, image, &TextureSettings::new())
+            .map_err(|error| format!("{:?}", error))
+    }
+}
+
+impl TextureLoader for GliumWindow {
+    type Texture = Texture;
+
+    fn load_texture(&mut self, path: &Path) -> Result<Texture, String> {
+        Texture::from_path(self, path, Flip::None, &TextureSettings::new())
+    }
+
+    fn load_image(&mut self, image: &RgbaImage) -> Result<Texture, String> {
+        Texture::from_image(self, image, &TextureSettings::new())
+            .map_err(|error| format!("{:?}", error))
+    }
+}
@@ -0,0 +1,234 @@
+//! Records the actions the player takes so a session can be played back
+//! without a window, ie to turn a bug report into a test
+
+use std::fs::File;
+use std::path::Path;
+
+use piston::input::ButtonState;
+use serde_json;
+
+use game_loop::GameLoop;
+use input::Action;
+use scene::SceneStack;
+
+/// An action pressed or released before an update
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedAction {
+    // how many updates into the recording it happened
+    tick: u64,
+    action: Action,
+    state: ButtonState,
+}
+
+impl RecordedAction {
+    // Getters
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn get_action(&self) -> Action {
+        self.action
+    }
+
+    pub fn get_state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// Every action taken during a session, in order
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Recording {
+    // how many updates the recording covers
+    length: u64,
+    actions: Vec<RecordedAction>,
+}
+
+impl Recording {
+    pub fn new() -> Recording {
+        Recording {
+            length: 0,
+            actions: Vec::new(),
+        }
+    }
+
+    /// Reads a recording from a json file
+    pub fn load(path: &Path) -> Result<Recording, String> {
+        let file = File::open(path).map_err(|error| format!("{:?}: {}", path, error))?;
+        serde_json::from_reader(file).map_err(|error| format!("{:?}: {}", path, error))
+    }
+
+    /// Writes the recording to a json file
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|error| format!("{:?}: {}", path, error))?;
+        serde_json::to_writer_pretty(file, self).map_err(|error| format!("{:?}: {}", path, error))
+    }
+
+    // Getters
+    pub fn get_length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn get_actions(&self) -> &[RecordedAction] {
+        &self.actions
+    }
+}
+
+impl Recording {
+    /// Adds an action taken before the update numbered tick
+    pub fn push(&mut self, tick: u64, action: Action, state: ButtonState) {
+        self.actions.push(RecordedAction {
+            tick,
+            action,
+            state,
+        });
+        self.length = self.length.max(tick);
+    }
+
+    /// Sets how many updates the recording covers
+    pub fn length(&mut self, ticks: u64) {
+        self.length = ticks;
+    }
+}
+
+/// Plays a recording back into a scene stack, taking the player's place
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    recording: Recording,
+    // the next action to play
+    next: usize,
+    // how many updates have been played
+    tick: u64,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Replay {
+        Replay {
+            recording,
+            next: 0,
+            tick: 0,
+        }
+    }
+
+    // Getters
+    pub fn get_recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.recording.length && self.next >= self.recording.actions.len()
+    }
+}
+
+impl Replay {
+    /// Takes the actions recorded for the next update, then runs it
+    pub fn step<T>(&mut self, stack: &mut SceneStack<T>, game_loop: &mut GameLoop) {
+        self.take_actions(stack);
+        game_loop.step(stack);
+        self.tick += 1;
+    }
+
+    /// Plays the rest of the recording. Returns how many updates ran
+    pub fn run<T>(&mut self, stack: &mut SceneStack<T>, game_loop: &mut GameLoop) -> u64 {
+        let start = self.tick;
+        while self.tick < self.recording.length {
+            self.step(stack, game_loop);
+        }
+
+        // anything let go of after the last update
+        self.take_actions(stack);
+        self.tick - start
+    }
+
+    fn take_actions<T>(&mut self, stack: &mut SceneStack<T>) {
+        while let Some(&recorded) = self.recording.actions.get(self.next) {
+            if recorded.tick > self.tick {
+                break;
+            }
+
+            stack.handle_action(recorded.action, recorded.state);
+            self.next += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::RgbaImage;
+    use piston::input::{Button, Key};
+    use tempfile::TempDir;
+
+    use game::tests::game;
+    use inventory::Inventory;
+    use scene::Title;
+
+    fn stack() -> SceneStack<RgbaImage> {
+        SceneStack::new(game(), Box::new(Title::new()))
+    }
+
+    fn fixture(name: &str) -> Recording {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/replays")
+            .join(name);
+        Recording::load(&path).unwrap()
+    }
+
+    #[test]
+    fn a_recorded_session_plays_back_the_same() {
+        let mut game_loop = GameLoop::default();
+        let live = &mut stack();
+        live.get_mut_game().get_mut_input().record();
+
+        let mut tap = |stack: &mut SceneStack<RgbaImage>, key: Key, ticks: u32| {
+            stack.handle_input(Button::Keyboard(key), ButtonState::Press);
+            for _ in 0..ticks {
+                game_loop.step(stack);
+            }
+            stack.handle_input(Button::Keyboard(key), ButtonState::Release);
+            game_loop.step(stack);
+        };
+        tap(live, Key::Return, 1);
+        tap(live, Key::Right, 20);
+        tap(live, Key::Down, 1);
+
+        let recording = live.get_mut_game().get_mut_input().stop_recording();
+        let recording = recording.unwrap();
+        assert_eq!(recording.get_length(), 25);
+
+        // through a file and back, as a bug report would arrive
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("replay.json");
+        recording.save(&path).unwrap();
+        let loaded = Recording::load(&path);
+
+        let replayed = &mut stack();
+        let mut replay = Replay::new(loaded.unwrap());
+        assert_eq!(replay.run(replayed, &mut GameLoop::default()), 25);
+        assert!(replay.is_finished());
+
+        let (live, replayed) = (live.get_game(), replayed.get_game());
+        assert_eq!(replayed.get_player(), live.get_player());
+        assert_eq!(replayed.get_player().get_tile(), (6, 1));
+        assert_eq!(
+            replayed.get_world().get_current_name(),
+            live.get_world().get_current_name()
+        );
+    }
+
+    #[test]
+    fn walking_into_the_house() {
+        let stack = &mut stack();
+        Replay::new(fixture("walk_to_house.json")).run(stack, &mut GameLoop::default());
+
+        let game = stack.get_game();
+        assert_eq!(stack.get_names(), vec!["overworld"]);
+        assert_eq!(game.get_world().get_current_name(), Some("house.tmx"));
+        assert_eq!(game.get_player().get_tile(), (2, 3));
+        assert_eq!(game.get_inventory(), &Inventory::new());
+    }
+}
@@ -0,0 +1,441 @@
+//! Upgrades save documents written by older builds. Each migration takes a
+//! document from one version to the next, and they run one after another
+//! until the document is current, so old saves only ever need to be handled
+//! once
+
+use std::cmp;
+
+use serde_json::{Map, Value};
+
+use inventory::DEFAULT_CAPACITY;
+
+use super::SAVE_VERSION;
+
+/// The equipment slots of a version 5 party member
+const V5_SLOTS: [&str; 4] = ["weapon", "body", "arms", "other"];
+
+/// The stats of a version 6 party member, as they are written out
+const V6_STATS: [&str; 9] = [
+    "hp", "pp", "offense", "defense", "speed", "guts", "vitality", "iq", "luck",
+];
+
+/// A step from one version of the save format to the next
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+/// The migration at index i upgrades a version i + 1 document to version i + 2
+const MIGRATIONS: [Migration; 7] = [
+    v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5, v5_to_v6, v6_to_v7, v7_to_v8,
+];
+
+/// Brings a save document up to the current version
+pub fn migrate(mut document: Value) -> Result<Value, String> {
+    let mut version = get_version(&document)?;
+
+    if version > SAVE_VERSION {
+        return Err(format!(
+            "the save is version {} but only up to version {} can be read",
+            version, SAVE_VERSION
+        ));
+    }
+
+    {
+        let object = match document.as_object_mut() {
+            Some(object) => object,
+            None => return Err("the save is not a json object".to_string()),
+        };
+
+        while version < SAVE_VERSION {
+            let migration = MIGRATIONS[version as usize - 1];
+            migration(object)
+                .map_err(|error| format!("upgrading from version {}: {}", version, error))?;
+
+            version += 1;
+            object.insert("version".to_string(), Value::from(version));
+        }
+    }
+
+    Ok(document)
+}
+
+/// Returns the version a save document says it is
+pub fn get_version(document: &Value) -> Result<u32, String> {
+    match document.get("version").and_then(|version| version.as_u64()) {
+        Some(version) if version >= 1 => Ok(version as u32),
+        _ => Err("the save has no version".to_string()),
+    }
+}
+
+/// Version 2 remembers when the save was written. Older saves are dated
+/// to the start of the epoch
+fn v1_to_v2(save: &mut Map<String, Value>) -> Result<(), String> {
+    save.insert("saved_at".to_string(), Value::from(0));
+    Ok(())
+}
+
+/// Version 3 refers to items by id instead of keeping a copy of each one.
+/// Older saves only had the name, which the ids were made from
+fn v2_to_v3(save: &mut Map<String, Value>) -> Result<(), String> {
+    let bags = match save
+        .get_mut("inventory")
+        .and_then(|inventory| inventory.get_mut("bags"))
+    {
+        Some(&mut Value::Object(ref mut bags)) => bags,
+        _ => return Err("the save has no inventory".to_string()),
+    };
+
+    for entries in bags.values_mut() {
+        let entries = match *entries {
+            Value::Array(ref mut entries) => entries,
+            _ => return Err("an inventory bag is not a list".to_string()),
+        };
+
+        for entry in entries.iter_mut() {
+            let (qty, name) = match (
+                entry.get("qty").cloned(),
+                entry.pointer("/item/name").and_then(|name| name.as_str()),
+            ) {
+                (Some(qty), Some(name)) => (qty, name.to_string()),
+                _ => return Err(format!("an inventory entry has no item: {}", entry)),
+            };
+
+            let mut upgraded = Map::new();
+            upgraded.insert("qty".to_string(), qty);
+            upgraded.insert("id".to_string(), Value::from(id_from_name(&name)));
+            *entry = Value::Object(upgraded);
+        }
+    }
+
+    Ok(())
+}
+
+/// Version 4 limits how many slots the inventory has. Older saves get the
+/// default, or enough slots for what they already carry
+fn v3_to_v4(save: &mut Map<String, Value>) -> Result<(), String> {
+    let inventory = match save.get_mut("inventory") {
+        Some(&mut Value::Object(ref mut inventory)) => inventory,
+        _ => return Err("the save has no inventory".to_string()),
+    };
+
+    let used: usize = match inventory.get("bags").and_then(|bags| bags.as_object()) {
+        Some(bags) => bags
+            .values()
+            .map(|entries| entries.as_array().map_or(0, |entries| entries.len()))
+            .sum(),
+        None => return Err("the save has no inventory".to_string()),
+    };
+
+    let capacity = cmp::max(used, DEFAULT_CAPACITY);
+    inventory.insert("capacity".to_string(), Value::from(capacity));
+    Ok(())
+}
+
+/// Version 5 keeps each party member's stats and equipment instead of just
+/// their name. Older saves had nothing equipped
+fn v4_to_v5(save: &mut Map<String, Value>) -> Result<(), String> {
+    let names = match save.get("party").and_then(|party| party.as_array()) {
+        Some(names) => names.clone(),
+        None => return Err("the save has no party".to_string()),
+    };
+
+    let mut party = Vec::new();
+    for name in names {
+        let name = match name.as_str() {
+            Some(name) => name,
+            None => return Err(format!("a party member has no name: {}", name)),
+        };
+        let mut equipment = Map::new();
+        for &slot in V5_SLOTS.iter() {
+            equipment.insert(slot.to_string(), Value::Null);
+        }
+
+        let mut character = Map::new();
+        character.insert("name".to_string(), Value::from(name));
+        character.insert("base".to_string(), Value::Object(Map::new()));
+        character.insert("equipment".to_string(), Value::Object(equipment));
+        party.push(Value::Object(character));
+    }
+
+    save.insert("party".to_string(), Value::Array(party));
+    Ok(())
+}
+
+/// Version 6 has a level and typed stats for each party member, with base
+/// stats that were kept by name moved to their stat
+fn v5_to_v6(save: &mut Map<String, Value>) -> Result<(), String> {
+    let party = match save.get_mut("party") {
+        Some(&mut Value::Array(ref mut party)) => party,
+        _ => return Err("the save has no party".to_string()),
+    };
+
+    for character in party.iter_mut() {
+        let character = match *character {
+            Value::Object(ref mut character) => character,
+            _ => return Err(format!("a party member is not an object: {}", character)),
+        };
+
+        let mut base = v6_stats();
+        if let Some(old) = character.get("base").and_then(|base| base.as_object()) {
+            for (name, value) in old {
+                let stat =
+                    stat_from_name(name).ok_or_else(|| format!("unknown stat {:?}", name))?;
+                base.insert(stat.to_string(), Value::from(value.as_i64().unwrap_or(0)));
+            }
+        }
+
+        character.insert("level".to_string(), Value::from(1));
+        character.insert("base".to_string(), Value::Object(base));
+        character.insert("growth".to_string(), Value::Object(v6_stats()));
+        character.insert("modifiers".to_string(), Value::Array(Vec::new()));
+    }
+
+    Ok(())
+}
+
+/// Version 7 keeps how much HP and PP each party member has left. Older
+/// saves start everyone fully recovered
+fn v6_to_v7(save: &mut Map<String, Value>) -> Result<(), String> {
+    let party = match save.get_mut("party") {
+        Some(&mut Value::Array(ref mut party)) => party,
+        _ => return Err("the save has no party".to_string()),
+    };
+
+    for character in party.iter_mut() {
+        let character = match *character {
+            Value::Object(ref mut character) => character,
+            _ => return Err(format!("a party member is not an object: {}", character)),
+        };
+
+        let level = character
+            .get("level")
+            .and_then(|level| level.as_i64())
+            .unwrap_or(1);
+        let stat = |field: &str, stat: &str| match character
+            .get(field)
+            .and_then(|stats| stats.as_object())
+        {
+            Some(stats) => Ok(stats
+                .get(stat)
+                .and_then(|value| value.as_i64())
+                .unwrap_or(0)),
+            None => Err(format!("a party member has no {} stats", field)),
+        };
+        // the stats they have at their level, before equipment
+        let natural = |name: &str| -> Result<i64, String> {
+            Ok(stat("base", name)? + stat("growth", name)? * (level - 1))
+        };
+        let (hp, pp) = (natural("hp")?, natural("pp")?);
+
+        character.insert("hp".to_string(), Value::from(hp));
+        character.insert("pp".to_string(), Value::from(pp));
+    }
+
+    Ok(())
+}
+
+/// Version 8 counts modifiers down in turns or seconds instead of only
+/// turns, and keeps the statuses each party member has. Older saves had none
+fn v7_to_v8(save: &mut Map<String, Value>) -> Result<(), String> {
+    let party = match save.get_mut("party") {
+        Some(&mut Value::Array(ref mut party)) => party,
+        _ => return Err("the save has no party".to_string()),
+    };
+
+    for character in party.iter_mut() {
+        let character = match *character {
+            Value::Object(ref mut character) => character,
+            _ => return Err(format!("a party member is not an object: {}", character)),
+        };
+
+        if let Some(&mut Value::Array(ref mut modifiers)) = character.get_mut("modifiers") {
+            for modifier in modifiers
+                .iter_mut()
+                .filter_map(|modifier| modifier.as_object_mut())
+            {
+                let turns = modifier.remove("turns").unwrap_or_else(|| Value::from(0));
+                let mut duration = Map::new();
+                duration.insert("Turns".to_string(), turns);
+                modifier.insert("duration".to_string(), Value::Object(duration));
+            }
+        }
+
+        let mut statuses = Map::new();
+        statuses.insert("afflictions".to_string(), Value::Array(Vec::new()));
+        character.insert("statuses".to_string(), Value::Object(statuses));
+    }
+
+    Ok(())
+}
+
+/// Returns version 6 stats that are all 0
+fn v6_stats() -> Map<String, Value> {
+    V6_STATS
+        .iter()
+        .map(|stat| (stat.to_string(), Value::from(0)))
+        .collect()
+}
+
+/// Finds the version 6 stat a version 5 save meant by a name
+fn stat_from_name(name: &str) -> Option<&'static str> {
+    V6_STATS
+        .iter()
+        .cloned()
+        .find(|stat| stat.eq_ignore_ascii_case(name))
+        .or(match name {
+            "Off" => Some("offense"),
+            "Def" => Some("defense"),
+            "Spd" => Some("speed"),
+            "Vit" => Some("vitality"),
+            "Ap" => Some("pp"),
+            _ => None,
+        })
+}
+
+/// Turns an item name into the id it was given, ie "Bread Roll" into
+/// "bread_roll"
+fn id_from_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::path::Path;
+
+    use game::tests::game;
+    use serde_json;
+
+    use character::Character;
+    use save::SaveGame;
+    use stats::{Duration, Modifier, Stat};
+    use status::StatusTracker;
+
+    fn fixture(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/saves")
+            .join(name);
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn every_version_has_a_migration() {
+        assert_eq!(MIGRATIONS.len() as u32, SAVE_VERSION - 1);
+    }
+
+    #[test]
+    fn version_1_saves_still_load() {
+        let save = SaveGame::from_json(&fixture("v1.json")).unwrap();
+
+        assert_eq!(save.get_version(), SAVE_VERSION);
+        assert_eq!(save.get_saved_at(), 0);
+        assert_eq!(save.get_map(), "house.tmx");
+        assert_eq!(save.get_position(), (2, 3));
+        assert_eq!(save.get_party(), &[Character::new("Ness")]);
+        assert_eq!(save.get_inventory().get_wallet(), 20);
+        assert_eq!(save.get_flags(), &["met_pokey".to_string()]);
+
+        let ids: Vec<&str> = save
+            .get_inventory()
+            .get_entries()
+            .map(|entry| entry.get_id().as_str())
+            .collect();
+        assert_eq!(ids, vec!["cookie"]);
+    }
+
+    #[test]
+    fn version_2_saves_refer_to_items_by_id() {
+        let save = SaveGame::from_json(&fixture("v2.json")).unwrap();
+
+        assert_eq!(save.get_saved_at(), 1_538_000_000);
+        assert_eq!(save.get_inventory().get_capacity(), DEFAULT_CAPACITY);
+        let entries: Vec<(&str, i32)> = save
+            .get_inventory()
+            .get_entries()
+            .map(|entry| (entry.get_id().as_str(), entry.get_qty()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![("baseball_cap", 1), ("bread_roll", 3), ("cracked_bat", 1)]
+        );
+
+        // and the items are there to restore them
+        let mut game = game();
+        save.restore(&mut game).unwrap();
+        assert_eq!(game.get_inventory(), save.get_inventory());
+    }
+
+    #[test]
+    fn version_4_names_become_version_5_characters() {
+        let mut save = serde_json::from_str::<Value>(r#"{ "party": ["Ness"] }"#).unwrap();
+        v4_to_v5(save.as_object_mut().unwrap()).unwrap();
+
+        // written out as version 5 had them, whatever characters look like now
+        let expected = serde_json::from_str::<Value>(
+            r#"{ "name": "Ness", "base": {}, "equipment": { "weapon": null, "body": null, "arms": null, "other": null } }"#,
+        )
+        .unwrap();
+        assert_eq!(save["party"][0], expected);
+    }
+
+    #[test]
+    fn version_5_characters_get_typed_stats_and_hp() {
+        let mut save = serde_json::from_str::<Value>(
+            r#"{ "party": [{ "name": "Ness", "base": { "Off": 4, "Luck": 3, "HP": 30 }, "equipment": {} }] }"#,
+        )
+        .unwrap();
+        v5_to_v6(save.as_object_mut().unwrap()).unwrap();
+        v6_to_v7(save.as_object_mut().unwrap()).unwrap();
+        v7_to_v8(save.as_object_mut().unwrap()).unwrap();
+
+        let ness: Character = serde_json::from_value(save["party"][0].clone()).unwrap();
+        assert_eq!(ness.get_level(), 1);
+        assert_eq!(ness.get_stat(Stat::Offense), 4);
+        assert_eq!(ness.get_stat(Stat::Luck), 3);
+        // and version 7 starts them at full health
+        assert_eq!(ness.get_hp(), 30);
+
+        let mut save = serde_json::from_str::<Value>(
+            r#"{ "party": [{ "name": "Ness", "base": { "Charm": 1 }, "equipment": {} }] }"#,
+        )
+        .unwrap();
+        let error = v5_to_v6(save.as_object_mut().unwrap()).unwrap_err();
+        assert!(error.contains("Charm"), "{}", error);
+    }
+
+    #[test]
+    fn version_7_modifiers_last_for_turns() {
+        let mut save = serde_json::from_str::<Value>(
+            r#"{ "party": [{ "name": "Ness", "modifiers": [{ "stat": "Speed", "amount": 2, "turns": 3 }] }] }"#,
+        )
+        .unwrap();
+        v7_to_v8(save.as_object_mut().unwrap()).unwrap();
+
+        let ness = &save["party"][0];
+        let modifier: Modifier = serde_json::from_value(ness["modifiers"][0].clone()).unwrap();
+        assert_eq!(modifier.get_duration(), Some(Duration::Turns(3)));
+        let statuses: StatusTracker = serde_json::from_value(ness["statuses"].clone()).unwrap();
+        assert!(statuses.is_empty());
+    }
+
+    #[test]
+    fn documents_without_a_version_are_rejected() {
+        let parse = |json: &str| serde_json::from_str::<Value>(json).unwrap();
+
+        let error = migrate(parse(r#"{ "map": "town.tmx" }"#)).unwrap_err();
+        assert!(error.contains("no version"), "{}", error);
+
+        let newer = format!(r#"{{ "version": {} }}"#, SAVE_VERSION + 1);
+        let error = migrate(parse(&newer)).unwrap_err();
+        assert!(error.contains("only up to"), "{}", error);
+    }
+}
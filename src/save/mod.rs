@@ -0,0 +1,343 @@
+//! Saving and loading a game in progress. Saves live in numbered slots in
+//! the user's data directory, one json file per slot
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use dirs;
+use serde_json;
+use tempfile::NamedTempFile;
+
+use character::Character;
+use game::Game;
+use inventory::Inventory;
+use player::Direction;
+
+mod migrate;
+
+pub use self::migrate::migrate;
+
+/// The version of the save format written by this build. Bump it whenever
+/// SaveGame changes shape, and add a migration from the last version
+pub const SAVE_VERSION: u32 = 8;
+
+/// Everything needed to pick a game back up where it was left
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    version: u32,
+    party: Vec<Character>,
+    inventory: Inventory,
+    // the map the player is on and the tile they stand on
+    map: String,
+    position: (i32, i32),
+    facing: Direction,
+    // sorted so saving the same game twice writes the same file
+    flags: Vec<String>,
+    // in seconds
+    play_time: f64,
+    // when the save was written, in seconds since the unix epoch
+    saved_at: u64,
+}
+
+impl SaveGame {
+    /// Takes a snapshot of a game. Fails if no map has been entered yet
+    pub fn capture<T>(game: &Game<T>) -> Result<SaveGame, String> {
+        let map = match game.get_world().get_current_name() {
+            Some(map) => map.to_string(),
+            None => return Err("there is no map to save on".to_string()),
+        };
+
+        let mut flags: Vec<String> = game.get_flags().iter().cloned().collect();
+        flags.sort();
+
+        Ok(SaveGame {
+            version: SAVE_VERSION,
+            party: game.get_party().to_vec(),
+            inventory: game.get_inventory().clone(),
+            map,
+            position: game.get_player().get_tile(),
+            facing: game.get_player().get_facing(),
+            flags,
+            play_time: game.get_play_time(),
+            saved_at: 0,
+        })
+    }
+
+    /// Puts a game back the way it was when the snapshot was taken. Fails
+    /// if the save holds or has equipped items the game does not have
+    pub fn restore<T>(&self, game: &mut Game<T>) -> Result<(), String> {
+        self.inventory.check(game.get_items())?;
+        for character in &self.party {
+            character.check(game.get_items())?;
+        }
+        game.enter_at(&self.map, self.position.0, self.position.1)?;
+        game.get_mut_player().face(self.facing);
+
+        game.party(self.party.clone());
+        game.inventory(self.inventory.clone());
+        game.flags(self.flags.iter().cloned().collect::<HashSet<String>>());
+        game.play_time(self.play_time);
+        Ok(())
+    }
+
+    /// Reads a save from a json document. Saves from older builds are
+    /// upgraded, saves from newer ones are rejected
+    pub fn from_json(json: &str) -> Result<SaveGame, String> {
+        let document = serde_json::from_str(json).map_err(|error| error.to_string())?;
+        serde_json::from_value(migrate(document)?).map_err(|error| error.to_string())
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|error| error.to_string())
+    }
+
+    // Getters
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn get_party(&self) -> &[Character] {
+        &self.party
+    }
+
+    pub fn get_inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    pub fn get_map(&self) -> &str {
+        &self.map
+    }
+
+    pub fn get_position(&self) -> (i32, i32) {
+        self.position
+    }
+
+    pub fn get_facing(&self) -> Direction {
+        self.facing
+    }
+
+    pub fn get_flags(&self) -> &[String] {
+        &self.flags
+    }
+
+    pub fn get_play_time(&self) -> f64 {
+        self.play_time
+    }
+
+    pub fn get_saved_at(&self) -> u64 {
+        self.saved_at
+    }
+}
+
+/// The numbered slots saves are written to
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveSlots {
+    directory: PathBuf,
+    // how many slots there are, numbered from 0
+    count: usize,
+}
+
+impl SaveSlots {
+    /// Keeps count slots in a directory. The directory is created on the
+    /// first save
+    pub fn new(directory: PathBuf, count: usize) -> SaveSlots {
+        SaveSlots { directory, count }
+    }
+
+    /// Keeps count slots in the user's data directory, ie
+    /// ~/.local/share/uranusbound/saves on Linux
+    pub fn in_data_dir(count: usize) -> Result<SaveSlots, String> {
+        match dirs::data_dir() {
+            Some(data) => Ok(SaveSlots::new(
+                data.join("uranusbound").join("saves"),
+                count,
+            )),
+            None => Err("could not find a data directory to save in".to_string()),
+        }
+    }
+
+    // Getters
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns where a slot is saved, whether or not it has been yet
+    pub fn get_path(&self, slot: usize) -> PathBuf {
+        self.directory.join(format!("slot{}.json", slot))
+    }
+
+    /// Checks if something has been saved in a slot
+    pub fn is_used(&self, slot: usize) -> bool {
+        slot < self.count && self.get_path(slot).is_file()
+    }
+
+    /// Returns the slots that have something saved in them
+    pub fn get_used(&self) -> Vec<usize> {
+        (0..self.count).filter(|&slot| self.is_used(slot)).collect()
+    }
+}
+
+impl SaveSlots {
+    /// Writes a save to a slot. The file is written next to the slot and
+    /// then moved over it, so a crash part way through never leaves a
+    /// broken save behind
+    pub fn save(&self, slot: usize, save: &SaveGame) -> Result<(), String> {
+        self.check(slot)?;
+
+        let mut save = save.clone();
+        save.saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+        let json = save.to_json()?;
+
+        fs::create_dir_all(&self.directory)
+            .map_err(|error| format!("{:?}: {}", self.directory, error))?;
+
+        let mut file = NamedTempFile::new_in(&self.directory)
+            .map_err(|error| format!("{:?}: {}", self.directory, error))?;
+        file.write_all(json.as_bytes())
+            .and_then(|_| file.as_file().sync_all())
+            .map_err(|error| format!("{:?}: {}", file.path(), error))?;
+
+        let path = self.get_path(slot);
+        file.persist(&path)
+            .map_err(|error| format!("{:?}: {}", path, error))?;
+        Ok(())
+    }
+
+    /// Reads the save in a slot
+    pub fn load(&self, slot: usize) -> Result<SaveGame, String> {
+        self.check(slot)?;
+
+        let path = self.get_path(slot);
+        let json = fs::read_to_string(&path).map_err(|error| format!("{:?}: {}", path, error))?;
+
+        SaveGame::from_json(&json).map_err(|error| format!("{:?}: {}", path, error))
+    }
+
+    /// Empties a slot
+    pub fn delete(&self, slot: usize) -> Result<(), String> {
+        self.check(slot)?;
+
+        let path = self.get_path(slot);
+        fs::remove_file(&path).map_err(|error| format!("{:?}: {}", path, error))
+    }
+
+    fn check(&self, slot: usize) -> Result<(), String> {
+        if slot < self.count {
+            Ok(())
+        } else {
+            Err(format!(
+                "there is no save slot {}, there are {}",
+                slot, self.count
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::TempDir;
+
+    use game::tests::game;
+    use stats::{Duration, Stat};
+    use status::Status;
+
+    fn slots() -> (TempDir, SaveSlots) {
+        let directory = TempDir::new().unwrap();
+        let slots = SaveSlots::new(directory.path().join("saves"), 3);
+        (directory, slots)
+    }
+
+    #[test]
+    fn a_saved_game_is_restored() {
+        let mut saved = game();
+        let mut ness = Character::new("Ness");
+        ness.level(3);
+        ness.modify(Stat::Speed, 2, Some(Duration::Turns(3)));
+        ness.inflict(Status::Poison, None);
+        saved.party(vec![ness, Character::new("Paula")]);
+        saved.flag("met_pokey");
+        saved.play_time(75.5);
+        let cookie = saved.get_items().get("cookie").unwrap().clone();
+        saved.get_mut_inventory().add_item(2, &cookie).unwrap();
+        let bat = saved.get_items().get("cracked_bat").unwrap().clone();
+        saved.get_mut_inventory().add_item(1, &bat).unwrap();
+        saved.equip("Ness", "cracked_bat").unwrap();
+        saved.place_player(64.0, 0.0);
+        saved.travel().unwrap();
+        saved.get_mut_player().face(Direction::Left);
+
+        let (_directory, slots) = slots();
+        let save = SaveGame::capture(&saved).unwrap();
+        slots.save(1, &save).unwrap();
+        assert_eq!(slots.get_used(), vec![1]);
+
+        // a fresh game in a different spot picks up where the save left off
+        let mut loaded = game();
+        slots.load(1).unwrap().restore(&mut loaded).unwrap();
+
+        assert_eq!(loaded.get_world().get_current_name(), Some("house.tmx"));
+        assert_eq!(loaded.get_player().get_tile(), (2, 3));
+        assert_eq!(loaded.get_player().get_facing(), Direction::Left);
+        assert_eq!(loaded.get_party(), saved.get_party());
+        assert_eq!(
+            loaded
+                .get_character("Ness")
+                .unwrap()
+                .get_stat(Stat::Offense),
+            4
+        );
+        assert_eq!(loaded.get_inventory(), saved.get_inventory());
+        assert!(loaded.has_flag("met_pokey"));
+        assert_eq!(loaded.get_play_time(), 75.5);
+        assert_eq!(SaveGame::capture(&loaded).unwrap(), save);
+    }
+
+    #[test]
+    fn saving_again_replaces_the_slot() {
+        let (_directory, slots) = slots();
+        let mut game = game();
+        slots.save(0, &SaveGame::capture(&game).unwrap()).unwrap();
+
+        game.flag("second");
+        slots.save(0, &SaveGame::capture(&game).unwrap()).unwrap();
+
+        // nothing but the slot is left in the directory
+        let files = fs::read_dir(slots.get_directory()).unwrap().count();
+        assert_eq!(files, 1);
+        let loaded = slots.load(0).unwrap();
+        assert_eq!(loaded.get_flags(), &["second".to_string()]);
+        assert!(loaded.get_saved_at() > 0);
+
+        slots.delete(0).unwrap();
+        assert!(!slots.is_used(0));
+    }
+
+    #[test]
+    fn bad_saves_are_errors() {
+        let (_directory, slots) = slots();
+
+        assert!(slots.load(0).is_err());
+        assert!(slots.load(3).unwrap_err().contains("no save slot 3"));
+
+        fs::create_dir_all(slots.get_directory()).unwrap();
+        fs::write(slots.get_path(0), "{ not json").unwrap();
+        assert!(slots.load(0).is_err());
+
+        let mut newer = SaveGame::capture(&game()).unwrap();
+        newer.version = SAVE_VERSION + 1;
+        let error = SaveGame::from_json(&newer.to_json().unwrap()).unwrap_err();
+        assert!(error.contains("version"), "{}", error);
+    }
+}
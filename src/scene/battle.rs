@@ -0,0 +1,74 @@
+//! Fighting an enemy the player ran into
+
+use game::Game;
+use input::Action;
+use render::Renderer;
+
+use super::{draw_window, Scene, Transition};
+
+/// A battle against a single enemy. The player can keep fighting or run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Battle {
+    enemy: String,
+    // how many turns have been taken
+    turn: u32,
+}
+
+impl Battle {
+    pub fn new(enemy: &str) -> Battle {
+        Battle {
+            enemy: enemy.to_string(),
+            turn: 0,
+        }
+    }
+
+    // Getters
+    pub fn get_enemy(&self) -> &str {
+        &self.enemy
+    }
+
+    pub fn get_turn(&self) -> u32 {
+        self.turn
+    }
+}
+
+impl<T> Scene<T> for Battle {
+    fn get_name(&self) -> &str {
+        "battle"
+    }
+
+    fn handle_action(&mut self, game: &mut Game<T>, action: Action) -> Transition<T> {
+        match action {
+            Action::Confirm => {
+                self.turn += 1;
+                game.end_turn();
+            }
+            // running away always works for now
+            Action::Cancel => return Transition::Pop,
+            _ => (),
+        }
+
+        Transition::None
+    }
+
+    fn render(
+        &mut self,
+        game: &mut Game<T>,
+        renderer: &mut dyn Renderer<Texture = T>,
+        _alpha: f64,
+    ) {
+        let (width, height) = (
+            game.get_camera().get_width() as f64,
+            game.get_camera().get_height() as f64,
+        );
+
+        renderer.clear([0.3, 0.1, 0.4, 1.0]);
+
+        // the enemy in the middle and the command window along the top
+        renderer.fill_rect(
+            [0.8, 0.2, 0.2, 1.0],
+            [width / 2.0 - 16.0, height / 2.0 - 16.0, 32.0, 32.0],
+        );
+        draw_window(renderer, [8.0, 8.0, width - 16.0, 32.0]);
+    }
+}
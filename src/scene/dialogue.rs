@@ -0,0 +1,76 @@
+//! A box of text at the bottom of the screen, ie when reading a sign
+
+use game::Game;
+use input::Action;
+use render::Renderer;
+
+use super::{draw_window, Scene, Transition};
+
+/// Shows text a page at a time. Pages are separated by blank lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dialogue {
+    pages: Vec<String>,
+    page: usize,
+}
+
+impl Dialogue {
+    pub fn new(text: &str) -> Dialogue {
+        let pages = text
+            .split("\n\n")
+            .map(|page| page.trim().to_string())
+            .filter(|page| !page.is_empty())
+            .collect();
+
+        Dialogue { pages, page: 0 }
+    }
+
+    // Getters
+    pub fn get_pages(&self) -> &[String] {
+        &self.pages
+    }
+
+    /// Returns the page being shown
+    pub fn get_page(&self) -> Option<&str> {
+        self.pages.get(self.page).map(|page| page.as_str())
+    }
+}
+
+impl<T> Scene<T> for Dialogue {
+    fn get_name(&self) -> &str {
+        "dialogue"
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn handle_action(&mut self, _game: &mut Game<T>, action: Action) -> Transition<T> {
+        // confirm turns the page, the box closes after the last one
+        if action == Action::Confirm || action == Action::Cancel {
+            self.page += 1;
+        }
+
+        if self.page >= self.pages.len() {
+            Transition::Pop
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(
+        &mut self,
+        game: &mut Game<T>,
+        renderer: &mut dyn Renderer<Texture = T>,
+        _alpha: f64,
+    ) {
+        let (width, height) = (
+            game.get_camera().get_width() as f64,
+            game.get_camera().get_height() as f64,
+        );
+
+        draw_window(
+            renderer,
+            [8.0, height * 2.0 / 3.0, width - 16.0, height / 3.0 - 8.0],
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Menus opened from the overworld
+
+use game::Game;
+use input::Action;
+use render::Renderer;
+
+use super::{draw_window, Scene, Transition};
+
+// how tall each line of a menu is
+const LINE_HEIGHT: f64 = 16.0;
+
+/// The options in the pause menu, top to bottom
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PauseOption {
+    Items,
+    Close,
+}
+
+const PAUSE_OPTIONS: [PauseOption; 2] = [PauseOption::Items, PauseOption::Close];
+
+/// The menu opened while walking around
+#[derive(Debug, Default)]
+pub struct PauseMenu {
+    // index of the highlighted option
+    cursor: usize,
+}
+
+impl PauseMenu {
+    pub fn new() -> PauseMenu {
+        PauseMenu { cursor: 0 }
+    }
+
+    // Getters
+    /// Returns the highlighted option
+    pub fn get_selected(&self) -> PauseOption {
+        PAUSE_OPTIONS[self.cursor]
+    }
+}
+
+impl<T> Scene<T> for PauseMenu {
+    fn get_name(&self) -> &str {
+        "pause menu"
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn handle_action(&mut self, _game: &mut Game<T>, action: Action) -> Transition<T> {
+        match action {
+            Action::Cancel => Transition::Pop,
+            Action::Confirm => match self.get_selected() {
+                PauseOption::Items => Transition::Push(Box::new(InventoryScreen::new())),
+                PauseOption::Close => Transition::Pop,
+            },
+            action => {
+                self.cursor = move_cursor(self.cursor, PAUSE_OPTIONS.len(), action);
+                Transition::None
+            }
+        }
+    }
+
+    fn render(
+        &mut self,
+        game: &mut Game<T>,
+        renderer: &mut dyn Renderer<Texture = T>,
+        _alpha: f64,
+    ) {
+        let width = game.get_camera().get_width() as f64;
+        let rect = [
+            width - 72.0,
+            8.0,
+            64.0,
+            PAUSE_OPTIONS.len() as f64 * LINE_HEIGHT + 8.0,
+        ];
+
+        draw_window(renderer, rect);
+        draw_cursor(renderer, rect, self.cursor);
+    }
+}
+
+/// Lists what the player is carrying
+#[derive(Debug, Default)]
+pub struct InventoryScreen;
+
+impl InventoryScreen {
+    pub fn new() -> InventoryScreen {
+        InventoryScreen
+    }
+}
+
+impl<T> Scene<T> for InventoryScreen {
+    fn get_name(&self) -> &str {
+        "inventory"
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn handle_action(&mut self, _game: &mut Game<T>, action: Action) -> Transition<T> {
+        match action {
+            Action::Cancel => Transition::Pop,
+            _ => Transition::None,
+        }
+    }
+
+    fn render(
+        &mut self,
+        game: &mut Game<T>,
+        renderer: &mut dyn Renderer<Texture = T>,
+        _alpha: f64,
+    ) {
+        let (width, height) = (
+            game.get_camera().get_width() as f64,
+            game.get_camera().get_height() as f64,
+        );
+        let rect = [8.0, 8.0, width - 96.0, height - 16.0];
+
+        draw_window(renderer, rect);
+    }
+}
+
+/// Moves a menu cursor up or down, wrapping around at either end
+fn move_cursor(cursor: usize, options: usize, action: Action) -> usize {
+    match action {
+        Action::MoveUp => (cursor + options - 1) % options,
+        Action::MoveDown => (cursor + 1) % options,
+        _ => cursor,
+    }
+}
+
+/// Highlights a line of a menu window
+fn draw_cursor<T>(renderer: &mut dyn Renderer<Texture = T>, window: [f64; 4], line: usize) {
+    renderer.fill_rect(
+        [1.0, 1.0, 1.0, 0.3],
+        [
+            window[0] + 4.0,
+            window[1] + 4.0 + line as f64 * LINE_HEIGHT,
+            window[2] - 8.0,
+            LINE_HEIGHT,
+        ],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_wraps_around() {
+        assert_eq!(move_cursor(0, 3, Action::MoveUp), 2);
+        assert_eq!(move_cursor(2, 3, Action::MoveDown), 0);
+        assert_eq!(move_cursor(1, 3, Action::Confirm), 1);
+    }
+}
@@ -0,0 +1,324 @@
+//! The game is a stack of scenes. Only the top scene gets input and
+//! updates, while overlays like menus let the scenes under them be drawn
+
+use glium_graphics::Texture;
+use piston::input::{Button, ButtonState, ControllerAxisArgs};
+
+use game::Game;
+use game_loop::Simulation;
+use input::Action;
+use render::Renderer;
+
+mod battle;
+mod dialogue;
+mod menu;
+mod overworld;
+mod title;
+
+pub use self::battle::Battle;
+pub use self::dialogue::Dialogue;
+pub use self::menu::{InventoryScreen, PauseMenu};
+pub use self::overworld::Overworld;
+pub use self::title::Title;
+
+/// What the scene stack should do after a scene has handled something
+pub enum Transition<T = Texture> {
+    // stay on the current scene
+    None,
+    // put a scene on top of the current one
+    Push(Box<dyn Scene<T>>),
+    // go back to the scene underneath
+    Pop,
+    // swap the current scene for another
+    Replace(Box<dyn Scene<T>>),
+    // close every scene, ending the game
+    Quit,
+}
+
+/// A state the game can be in, ie the title screen or a menu
+pub trait Scene<T = Texture> {
+    /// A short name for the scene, used in logs and tests
+    fn get_name(&self) -> &str;
+
+    /// Overlays are drawn on top of the scene underneath them instead of
+    /// hiding it
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Advances the scene by one fixed step of dt seconds
+    fn update(&mut self, _game: &mut Game<T>, _dt: f64) -> Transition<T> {
+        Transition::None
+    }
+
+    /// Reacts to an action being pressed. Actions that are held down can be
+    /// checked during update through the game's input
+    fn handle_action(&mut self, game: &mut Game<T>, action: Action) -> Transition<T>;
+
+    /// Draws the scene. alpha is how far the frame is between the last
+    /// update and the next
+    fn render(&mut self, game: &mut Game<T>, renderer: &mut dyn Renderer<Texture = T>, alpha: f64);
+}
+
+/// The scenes the game is in, with the game they all share
+pub struct SceneStack<T = Texture> {
+    scenes: Vec<Box<dyn Scene<T>>>,
+    game: Game<T>,
+}
+
+impl<T> SceneStack<T> {
+    /// Starts the game on a scene
+    pub fn new(game: Game<T>, first: Box<dyn Scene<T>>) -> SceneStack<T> {
+        SceneStack {
+            scenes: vec![first],
+            game,
+        }
+    }
+
+    // Getters
+    pub fn get_game(&self) -> &Game<T> {
+        &self.game
+    }
+
+    pub fn get_mut_game(&mut self) -> &mut Game<T> {
+        &mut self.game
+    }
+
+    /// Returns the names of the scenes from the bottom of the stack up
+    pub fn get_names(&self) -> Vec<&str> {
+        self.scenes.iter().map(|scene| scene.get_name()).collect()
+    }
+
+    /// Returns the name of the scene getting input
+    pub fn get_top_name(&self) -> Option<&str> {
+        self.scenes.last().map(|scene| scene.get_name())
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    /// The game is over once there are no scenes left
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+}
+
+impl<T> SceneStack<T> {
+    /// Carries out what a scene asked for
+    pub fn apply(&mut self, transition: Transition<T>) {
+        match transition {
+            Transition::None => (),
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop => {
+                self.scenes.pop();
+            }
+            Transition::Replace(scene) => {
+                self.scenes.pop();
+                self.scenes.push(scene);
+            }
+            Transition::Quit => self.scenes.clear(),
+        }
+    }
+
+    /// Passes the actions a button presses to the top scene
+    pub fn handle_input(&mut self, button: Button, state: ButtonState) {
+        let actions = self.game.get_mut_input().button(button, state);
+        self.handle_actions(actions);
+    }
+
+    /// Passes the actions a controller stick presses to the top scene
+    pub fn handle_axis(&mut self, args: ControllerAxisArgs) {
+        let actions = self.game.get_mut_input().axis(args);
+        self.handle_actions(actions);
+    }
+
+    /// Presses or releases an action directly, ie when replaying a recording
+    pub fn handle_action(&mut self, action: Action, state: ButtonState) {
+        let actions = match state {
+            ButtonState::Press => self.game.get_mut_input().press_action(action),
+            ButtonState::Release => {
+                self.game.get_mut_input().release_action(action);
+                Vec::new()
+            }
+        };
+        self.handle_actions(actions);
+    }
+
+    /// Each action goes to whichever scene is on top by the time it is handled
+    fn handle_actions(&mut self, actions: Vec<Action>) {
+        for action in actions {
+            let transition = match self.scenes.last_mut() {
+                Some(scene) => scene.handle_action(&mut self.game, action),
+                None => return,
+            };
+
+            self.apply(transition);
+        }
+    }
+
+    /// Draws the top scene, along with the scenes under it as long as the
+    /// ones above them are overlays
+    pub fn render(&mut self, renderer: &mut dyn Renderer<Texture = T>, alpha: f64) {
+        let first = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+
+        for scene in &mut self.scenes[first..] {
+            scene.render(&mut self.game, renderer, alpha);
+        }
+    }
+}
+
+impl<T> Simulation for SceneStack<T> {
+    /// Only the top scene is updated, so the map stands still under a menu
+    fn update(&mut self, dt: f64) {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.update(&mut self.game, dt),
+            None => return,
+        };
+
+        self.apply(transition);
+        self.game.play(dt);
+        self.game.get_mut_input().end_frame();
+    }
+}
+
+/// Draws a menu window, a dark box with a light border
+pub fn draw_window<T>(renderer: &mut dyn Renderer<Texture = T>, rect: [f64; 4]) {
+    renderer.fill_rect([0.9, 0.9, 0.9, 1.0], rect);
+    renderer.fill_rect(
+        [0.1, 0.1, 0.2, 0.9],
+        [rect[0] + 2.0, rect[1] + 2.0, rect[2] - 4.0, rect[3] - 4.0],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::RgbaImage;
+    use piston::input::{ControllerButton, Key};
+
+    use input::Bindings;
+
+    use game::tests::game;
+    use render::SoftwareRenderer;
+
+    fn press(stack: &mut SceneStack<RgbaImage>, key: Key) {
+        stack.handle_input(Button::Keyboard(key), ButtonState::Press);
+        stack.handle_input(Button::Keyboard(key), ButtonState::Release);
+    }
+
+    fn stack() -> SceneStack<RgbaImage> {
+        SceneStack::new(game(), Box::new(Title::new()))
+    }
+
+    #[test]
+    fn title_leads_to_the_overworld() {
+        let stack = &mut stack();
+        assert_eq!(stack.get_names(), vec!["title"]);
+
+        press(stack, Key::Return);
+        assert_eq!(stack.get_names(), vec!["overworld"]);
+    }
+
+    #[test]
+    fn menus_open_over_the_map_and_close_again() {
+        let stack = &mut stack();
+        press(stack, Key::Return);
+
+        press(stack, Key::Tab);
+        assert_eq!(stack.get_names(), vec!["overworld", "pause menu"]);
+
+        // the first option opens the inventory on top of the menu
+        press(stack, Key::Return);
+        assert_eq!(
+            stack.get_names(),
+            vec!["overworld", "pause menu", "inventory"]
+        );
+
+        press(stack, Key::X);
+        press(stack, Key::X);
+        assert_eq!(stack.get_names(), vec!["overworld"]);
+    }
+
+    #[test]
+    fn the_map_does_not_move_under_a_menu() {
+        let stack = &mut stack();
+        press(stack, Key::Return);
+        press(stack, Key::Tab);
+
+        stack.handle_input(Button::Keyboard(Key::Down), ButtonState::Press);
+        for _ in 0..60 {
+            stack.update(1.0 / 60.0);
+        }
+
+        assert_eq!(stack.get_game().get_player().get_tile(), (4, 1));
+    }
+
+    #[test]
+    fn walking_through_a_door_changes_map() {
+        let stack = &mut stack();
+        press(stack, Key::Return);
+
+        stack.handle_input(Button::Keyboard(Key::Up), ButtonState::Press);
+        stack.update(1.0 / 60.0);
+        stack.handle_input(Button::Keyboard(Key::Up), ButtonState::Release);
+        for _ in 0..30 {
+            stack.update(1.0 / 60.0);
+        }
+
+        let game = stack.get_game();
+        assert_eq!(game.get_world().get_current_name(), Some("house.tmx"));
+        assert_eq!(game.get_player().get_tile(), (2, 3));
+    }
+
+    #[test]
+    fn overlays_are_drawn_over_the_scene_underneath() {
+        let stack = &mut stack();
+        press(stack, Key::Return);
+
+        let map_only = &mut SoftwareRenderer::new(160, 144);
+        stack.render(map_only, 0.0);
+
+        press(stack, Key::Tab);
+        let with_menu = &mut SoftwareRenderer::new(160, 144);
+        stack.render(with_menu, 0.0);
+
+        // the corner of the screen still shows the map, the menu covers the rest
+        let (map_only, with_menu) = (map_only.get_image(), with_menu.get_image());
+        assert_eq!(map_only.get_pixel(0, 143), with_menu.get_pixel(0, 143));
+        assert_ne!(map_only.get_pixel(150, 10), with_menu.get_pixel(150, 10));
+    }
+
+    #[test]
+    fn scenes_follow_remapped_controls() {
+        let stack = &mut stack();
+        let mut bindings = Bindings::default();
+        bindings.rebind(Action::Confirm, Button::Keyboard(Key::E));
+        stack.get_mut_game().get_mut_input().bindings(bindings);
+
+        press(stack, Key::Return);
+        assert_eq!(stack.get_names(), vec!["title"]);
+        press(stack, Key::E);
+        assert_eq!(stack.get_names(), vec!["overworld"]);
+
+        // start on any controller opens the menu
+        let start = Button::Controller(ControllerButton::new(2, 7));
+        stack.handle_input(start, ButtonState::Press);
+        assert_eq!(stack.get_top_name(), Some("pause menu"));
+    }
+
+    #[test]
+    fn quitting_empties_the_stack() {
+        let stack = &mut stack();
+        stack.apply(Transition::Push(Box::new(Dialogue::new("Hello"))));
+        stack.apply(Transition::Quit);
+
+        assert!(stack.is_empty());
+        assert_eq!(stack.get_top_name(), None);
+    }
+}
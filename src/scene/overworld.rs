@@ -0,0 +1,171 @@
+//! Walking around the map
+
+use game::Game;
+use game_loop::lerp;
+use input::Action;
+use render::Renderer;
+
+use super::{Battle, Dialogue, InventoryScreen, PauseMenu, Scene, Transition};
+
+/// Trigger events starting with this start a battle with the enemy named
+/// after it, ie "battle:slime"
+const BATTLE_EVENT: &str = "battle:";
+
+/// The map the player walks around on
+#[derive(Debug, Default)]
+pub struct Overworld {
+    // where the player was before the last update, for drawing between updates
+    previous: Option<(f64, f64)>,
+}
+
+impl Overworld {
+    pub fn new() -> Overworld {
+        Overworld { previous: None }
+    }
+}
+
+impl Overworld {
+    /// Checks what the player walked onto after finishing a step
+    fn arrive<T>(&mut self, game: &mut Game<T>) -> Transition<T> {
+        match game.travel() {
+            Ok(true) => {
+                // nothing to blend from on a new map
+                self.previous = None;
+                return Transition::None;
+            }
+            Ok(false) => (),
+            // the player stays where they are, and is told why
+            Err(error) => {
+                let text = format!("The way is blocked.\n\n{}", error);
+                return Transition::Push(Box::new(Dialogue::new(&text)));
+            }
+        }
+
+        let (x, y) = game.get_player_center();
+        let events: Vec<(String, bool)> = match game.get_world().get_current() {
+            Some(map) => map
+                .get_objects()
+                .triggers_at(x, y)
+                .map(|trigger| (trigger.get_event().to_string(), trigger.is_once()))
+                .collect(),
+            None => return Transition::None,
+        };
+
+        for (event, once) in events {
+            if once && !game.flag(&event) {
+                continue;
+            }
+
+            if let Some(enemy) = event.strip_prefix(BATTLE_EVENT) {
+                return Transition::Push(Box::new(Battle::new(enemy)));
+            }
+        }
+
+        Transition::None
+    }
+
+    /// Opens the sign the player is facing, if there is one
+    fn read<T>(&self, game: &Game<T>) -> Transition<T> {
+        let map = match game.get_world().get_current() {
+            Some(map) => map,
+            None => return Transition::None,
+        };
+
+        let (tile_w, tile_h) = game.get_tile_size();
+        let (x, y) = game.get_player().get_facing_tile();
+        let (x, y) = (
+            (x * tile_w) as f64 + tile_w as f64 / 2.0,
+            (y * tile_h) as f64 + tile_h as f64 / 2.0,
+        );
+
+        match map.get_objects().sign_at(x, y) {
+            Some(sign) => Transition::Push(Box::new(Dialogue::new(sign.get_text()))),
+            None => Transition::None,
+        }
+    }
+}
+
+impl<T> Scene<T> for Overworld {
+    fn get_name(&self) -> &str {
+        "overworld"
+    }
+
+    fn update(&mut self, game: &mut Game<T>, dt: f64) -> Transition<T> {
+        game.get_mut_world().update(dt);
+
+        let (tile_w, tile_h) = game.get_tile_size();
+        self.previous = Some(game.get_player().get_position(tile_w, tile_h));
+
+        // holding a direction walks tile after tile
+        if let Some(direction) = game.get_input().get_direction() {
+            game.walk_player(direction);
+        }
+
+        let was_moving = game.get_player().is_moving();
+        game.get_mut_player().update(dt);
+
+        let transition = if was_moving && !game.get_player().is_moving() {
+            self.arrive(game)
+        } else {
+            Transition::None
+        };
+
+        let (x, y) = game.get_player_center();
+        game.get_mut_camera().follow(x, y, dt);
+
+        transition
+    }
+
+    fn handle_action(&mut self, game: &mut Game<T>, action: Action) -> Transition<T> {
+        match action {
+            Action::Confirm => self.read(game),
+            Action::Menu => Transition::Push(Box::new(PauseMenu::new())),
+            Action::Inventory => Transition::Push(Box::new(InventoryScreen::new())),
+            _ => Transition::None,
+        }
+    }
+
+    fn render(&mut self, game: &mut Game<T>, renderer: &mut dyn Renderer<Texture = T>, alpha: f64) {
+        if game.get_world().get_current().is_none() {
+            return;
+        }
+
+        renderer.clear([0.0, 0.0, 0.0, 1.0]);
+
+        {
+            let (world, camera) = game.get_mut_view();
+            let map = world.get_current().unwrap();
+            for layer in map.get_ground_layers() {
+                renderer.draw_tiles(layer, map, camera);
+            }
+        }
+
+        let (tile_w, tile_h) = game.get_tile_size();
+        let player = game.get_player();
+        let current = player.get_position(tile_w, tile_h);
+        let previous = self.previous.unwrap_or(current);
+        let camera = game.get_camera();
+
+        // sprites stand on their tile and stick up over the one behind them
+        let src_rect = game
+            .get_sheet()
+            .src_rect(player.get_facing(), player.get_frame());
+        renderer.draw_image(
+            game.get_sprites(),
+            src_rect,
+            [
+                lerp(previous.0, current.0, alpha) - camera.get_x() as f64,
+                lerp(previous.1, current.1, alpha)
+                    - camera.get_y() as f64
+                    - (src_rect[3] - tile_h as f64),
+            ],
+            1.0,
+        );
+
+        let (world, camera) = game.get_mut_view();
+        let map = world.get_current().unwrap();
+        for layer in map.get_overhead_layers() {
+            renderer.draw_tiles(layer, map, camera);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! The first thing shown when the game starts
+
+use game::Game;
+use input::Action;
+use render::Renderer;
+
+use super::{draw_window, Overworld, Scene, Transition};
+
+/// Waits for the player to press start
+#[derive(Debug, Default)]
+pub struct Title;
+
+impl Title {
+    pub fn new() -> Title {
+        Title
+    }
+}
+
+impl<T> Scene<T> for Title {
+    fn get_name(&self) -> &str {
+        "title"
+    }
+
+    fn handle_action(&mut self, _game: &mut Game<T>, action: Action) -> Transition<T> {
+        if action == Action::Confirm {
+            return Transition::Replace(Box::new(Overworld::new()));
+        }
+
+        Transition::None
+    }
+
+    fn render(
+        &mut self,
+        game: &mut Game<T>,
+        renderer: &mut dyn Renderer<Texture = T>,
+        _alpha: f64,
+    ) {
+        let (width, height) = (
+            game.get_camera().get_width() as f64,
+            game.get_camera().get_height() as f64,
+        );
+
+        renderer.clear([0.05, 0.0, 0.15, 1.0]);
+        draw_window(
+            renderer,
+            [width / 4.0, height / 3.0, width / 2.0, height / 6.0],
+        );
+    }
+}
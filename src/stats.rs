@@ -0,0 +1,259 @@
+//! The numbers that describe how strong a character is, and the modifiers
+//! that raise or lower them for a while
+
+use std::ops::Add;
+
+/// One of a character's stats
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Stat {
+    // the most hit points and psychic points the character can have
+    Hp,
+    Pp,
+    Offense,
+    Defense,
+    Speed,
+    Guts,
+    Vitality,
+    Iq,
+    Luck,
+}
+
+/// Every stat, in the order they are listed in menus
+pub const STATS: [Stat; 9] = [
+    Stat::Hp,
+    Stat::Pp,
+    Stat::Offense,
+    Stat::Defense,
+    Stat::Speed,
+    Stat::Guts,
+    Stat::Vitality,
+    Stat::Iq,
+    Stat::Luck,
+];
+
+impl Stat {
+    /// Returns the name shown to the player
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Stat::Hp => "HP",
+            Stat::Pp => "PP",
+            Stat::Offense => "Offense",
+            Stat::Defense => "Defense",
+            Stat::Speed => "Speed",
+            Stat::Guts => "Guts",
+            Stat::Vitality => "Vitality",
+            Stat::Iq => "IQ",
+            Stat::Luck => "Luck",
+        }
+    }
+}
+
+/// A value for every stat
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    hp: i32,
+    pp: i32,
+    offense: i32,
+    defense: i32,
+    speed: i32,
+    guts: i32,
+    vitality: i32,
+    iq: i32,
+    luck: i32,
+}
+
+impl Stats {
+    /// Returns stats that are all 0
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    // Getters
+    pub fn get(&self, stat: Stat) -> i32 {
+        match stat {
+            Stat::Hp => self.hp,
+            Stat::Pp => self.pp,
+            Stat::Offense => self.offense,
+            Stat::Defense => self.defense,
+            Stat::Speed => self.speed,
+            Stat::Guts => self.guts,
+            Stat::Vitality => self.vitality,
+            Stat::Iq => self.iq,
+            Stat::Luck => self.luck,
+        }
+    }
+}
+
+impl Stats {
+    pub fn set(&mut self, stat: Stat, value: i32) {
+        *self.get_mut(stat) = value;
+    }
+
+    /// Raises a stat, or lowers it if amount is negative
+    pub fn raise(&mut self, stat: Stat, amount: i32) {
+        *self.get_mut(stat) += amount;
+    }
+
+    fn get_mut(&mut self, stat: Stat) -> &mut i32 {
+        match stat {
+            Stat::Hp => &mut self.hp,
+            Stat::Pp => &mut self.pp,
+            Stat::Offense => &mut self.offense,
+            Stat::Defense => &mut self.defense,
+            Stat::Speed => &mut self.speed,
+            Stat::Guts => &mut self.guts,
+            Stat::Vitality => &mut self.vitality,
+            Stat::Iq => &mut self.iq,
+            Stat::Luck => &mut self.luck,
+        }
+    }
+
+    /// Multiplies every stat, ie to work out the growth over several levels
+    pub fn times(&self, n: i32) -> Stats {
+        let mut stats = Stats::new();
+        for &stat in STATS.iter() {
+            stats.set(stat, self.get(stat) * n);
+        }
+        stats
+    }
+}
+
+impl Add for Stats {
+    type Output = Stats;
+
+    fn add(self, other: Stats) -> Stats {
+        let mut stats = self;
+        for &stat in STATS.iter() {
+            stats.raise(stat, other.get(stat));
+        }
+        stats
+    }
+}
+
+/// How long something lasts, counted in battle turns or in seconds of play
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Duration {
+    Turns(u32),
+    Seconds(f64),
+}
+
+impl Duration {
+    /// Counts down a turn. Durations in seconds are left alone. Returns
+    /// whether it has run out
+    pub fn tick_turn(&mut self) -> bool {
+        if let Duration::Turns(ref mut turns) = *self {
+            *turns = turns.saturating_sub(1);
+        }
+        self.is_over()
+    }
+
+    /// Counts down dt seconds. Durations in turns are left alone. Returns
+    /// whether it has run out
+    pub fn tick_seconds(&mut self, dt: f64) -> bool {
+        if let Duration::Seconds(ref mut seconds) = *self {
+            *seconds = (*seconds - dt).max(0.0);
+        }
+        self.is_over()
+    }
+
+    pub fn is_over(&self) -> bool {
+        match *self {
+            Duration::Turns(turns) => turns == 0,
+            Duration::Seconds(seconds) => seconds <= 0.0,
+        }
+    }
+}
+
+/// Raises or lowers a stat for a while, ie from a battle item
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Modifier {
+    stat: Stat,
+    amount: i32,
+    // how long is left before it wears off, or None to last until the
+    // modifiers are cleared
+    duration: Option<Duration>,
+}
+
+impl Modifier {
+    pub fn new(stat: Stat, amount: i32, duration: Option<Duration>) -> Modifier {
+        Modifier {
+            stat,
+            amount,
+            duration,
+        }
+    }
+
+    // Getters
+    pub fn get_stat(&self) -> Stat {
+        self.stat
+    }
+
+    pub fn get_amount(&self) -> i32 {
+        self.amount
+    }
+
+    pub fn get_duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// Counts down a turn. Returns whether the modifier has worn off
+    pub fn tick_turn(&mut self) -> bool {
+        self.duration
+            .as_mut()
+            .is_some_and(|duration| duration.tick_turn())
+    }
+
+    /// Counts down dt seconds. Returns whether the modifier has worn off
+    pub fn tick_seconds(&mut self, dt: f64) -> bool {
+        self.duration
+            .as_mut()
+            .is_some_and(|duration| duration.tick_seconds(dt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json;
+
+    #[test]
+    fn stats_add_up() {
+        let mut base = Stats::new();
+        base.set(Stat::Hp, 30);
+        base.set(Stat::Offense, 2);
+
+        let mut growth = Stats::new();
+        growth.set(Stat::Hp, 5);
+        growth.raise(Stat::Offense, 2);
+        growth.raise(Stat::Offense, -1);
+
+        let stats = base + growth.times(3);
+        assert_eq!(stats.get(Stat::Hp), 45);
+        assert_eq!(stats.get(Stat::Offense), 5);
+        assert_eq!(stats.get(Stat::Luck), 0);
+
+        // stats are written out by name
+        let json = serde_json::to_string(&stats).unwrap();
+        assert!(json.contains(r#""hp":45"#), "{}", json);
+    }
+
+    #[test]
+    fn modifiers_wear_off() {
+        let mut modifier = Modifier::new(Stat::Defense, 4, Some(Duration::Turns(2)));
+        assert!(!modifier.tick_turn());
+        assert!(!modifier.tick_seconds(60.0));
+        assert!(modifier.tick_turn());
+        assert_eq!(modifier.get_duration(), Some(Duration::Turns(0)));
+
+        let mut modifier = Modifier::new(Stat::Speed, 4, Some(Duration::Seconds(1.5)));
+        assert!(!modifier.tick_turn());
+        assert!(!modifier.tick_seconds(1.0));
+        assert!(modifier.tick_seconds(1.0));
+
+        // without a duration it lasts until it is cleared
+        let mut modifier = Modifier::new(Stat::Luck, 4, None);
+        assert!(!modifier.tick_turn());
+        assert!(!modifier.tick_seconds(1000.0));
+    }
+}
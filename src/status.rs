@@ -0,0 +1,163 @@
+//! Lasting conditions like poison, which stay on a character after the item
+//! or attack that caused them, until they wear off or are cured
+
+use stats::Duration;
+
+/// A condition a character can be suffering from
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Status {
+    // loses HP every turn
+    Poison,
+    // cannot act
+    Sleep,
+    // cannot act
+    Paralysis,
+    // sometimes misses a turn thinking of home
+    Homesick,
+}
+
+impl Status {
+    /// Returns the name shown to the player
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Status::Poison => "poisoned",
+            Status::Sleep => "asleep",
+            Status::Paralysis => "paralyzed",
+            Status::Homesick => "homesick",
+        }
+    }
+
+    /// Determines if a character with the status is kept from acting at all
+    pub fn prevents_action(self) -> bool {
+        matches!(self, Status::Sleep | Status::Paralysis)
+    }
+}
+
+/// A status a character has, and how long it has left
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Affliction {
+    status: Status,
+    // None lasts until it is cured
+    duration: Option<Duration>,
+}
+
+impl Affliction {
+    // Getters
+    pub fn get_status(&self) -> Status {
+        self.status
+    }
+
+    pub fn get_duration(&self) -> Option<Duration> {
+        self.duration
+    }
+}
+
+/// The statuses a character has, counted down as turns and time pass
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusTracker {
+    afflictions: Vec<Affliction>,
+}
+
+impl StatusTracker {
+    pub fn new() -> StatusTracker {
+        StatusTracker::default()
+    }
+
+    // Getters
+    pub fn get_afflictions(&self) -> &[Affliction] {
+        &self.afflictions
+    }
+
+    pub fn has(&self, status: Status) -> bool {
+        self.afflictions
+            .iter()
+            .any(|affliction| affliction.status == status)
+    }
+
+    /// Returns a status that keeps the character from acting, if they have
+    /// one
+    pub fn get_incapacitating(&self) -> Option<Status> {
+        self.afflictions
+            .iter()
+            .map(|affliction| affliction.status)
+            .find(|status| status.prevents_action())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.afflictions.is_empty()
+    }
+}
+
+impl StatusTracker {
+    /// Gives a status for a while, or until cured with None. Having it
+    /// again starts its duration over. Returns whether it is new
+    pub fn inflict(&mut self, status: Status, duration: Option<Duration>) -> bool {
+        let new = !self.has(status);
+        self.cure(status);
+        self.afflictions.push(Affliction { status, duration });
+        new
+    }
+
+    /// Takes a status away. Returns whether there was one to take
+    pub fn cure(&mut self, status: Status) -> bool {
+        let before = self.afflictions.len();
+        self.afflictions
+            .retain(|affliction| affliction.status != status);
+        self.afflictions.len() != before
+    }
+
+    pub fn clear(&mut self) {
+        self.afflictions.clear();
+    }
+
+    /// Counts down a turn. Returns the statuses that wore off
+    pub fn tick_turn(&mut self) -> Vec<Status> {
+        self.tick(|duration| duration.tick_turn())
+    }
+
+    /// Counts down dt seconds. Returns the statuses that wore off
+    pub fn tick_seconds(&mut self, dt: f64) -> Vec<Status> {
+        self.tick(|duration| duration.tick_seconds(dt))
+    }
+
+    fn tick<F: FnMut(&mut Duration) -> bool>(&mut self, mut tick: F) -> Vec<Status> {
+        let mut recovered = Vec::new();
+        self.afflictions.retain_mut(|affliction| {
+            let over = affliction.duration.as_mut().is_some_and(&mut tick);
+            if over {
+                recovered.push(affliction.status);
+            }
+            !over
+        });
+        recovered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_wear_off_or_are_cured() {
+        let mut statuses = StatusTracker::new();
+        assert!(statuses.inflict(Status::Sleep, Some(Duration::Turns(2))));
+        assert!(statuses.inflict(Status::Homesick, Some(Duration::Seconds(5.0))));
+        assert!(statuses.inflict(Status::Poison, None));
+
+        // being put to sleep again starts the count over
+        assert!(!statuses.inflict(Status::Sleep, Some(Duration::Turns(2))));
+        assert_eq!(statuses.get_afflictions().len(), 3);
+        assert_eq!(statuses.get_incapacitating(), Some(Status::Sleep));
+
+        assert_eq!(statuses.tick_turn(), vec![]);
+        assert_eq!(statuses.tick_turn(), vec![Status::Sleep]);
+        assert_eq!(statuses.tick_seconds(5.0), vec![Status::Homesick]);
+
+        // poison stays until it is cured
+        assert_eq!(statuses.tick_seconds(600.0), vec![]);
+        assert!(statuses.has(Status::Poison));
+        assert!(statuses.cure(Status::Poison));
+        assert!(!statuses.cure(Status::Poison));
+        assert!(statuses.is_empty());
+    }
+}
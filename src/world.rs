@@ -0,0 +1,368 @@
+//! Keeps track of the maps the player travels between
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use glium_graphics::Texture;
+use map::Map;
+use objects::Warp;
+use render::TextureLoader;
+
+/// The maps that have been loaded recently, so going back through a door
+/// does not load the map again. The least recently used maps are dropped
+/// once there are too many of them or their textures take up too much memory
+pub struct MapBuffer<T = Texture> {
+    maps: HashMap<String, Map<T>>,
+    // names from least to most recently used
+    order: VecDeque<String>,
+    // the most maps kept at once
+    capacity: usize,
+    // the most texture memory kept at once, in bytes
+    texture_budget: usize,
+}
+
+impl<T> MapBuffer<T> {
+    /// Creates an empty buffer
+    pub fn new(capacity: usize, texture_budget: usize) -> MapBuffer<T> {
+        MapBuffer {
+            maps: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+            texture_budget,
+        }
+    }
+
+    // Getters
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get_texture_budget(&self) -> usize {
+        self.texture_budget
+    }
+
+    pub fn len(&self) -> usize {
+        self.maps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.maps.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.maps.contains_key(name)
+    }
+
+    /// Returns the names of the maps held, from least to most recently used
+    pub fn get_names(&self) -> impl Iterator<Item = &String> {
+        self.order.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Map<T>> {
+        self.maps.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Map<T>> {
+        self.maps.get_mut(name)
+    }
+
+    /// Returns how much memory the textures of every held map take up
+    pub fn get_texture_bytes(&self) -> usize {
+        self.maps.values().map(|map| map.get_texture_bytes()).sum()
+    }
+}
+
+impl<T> MapBuffer<T> {
+    /// Sets the most maps kept at once
+    pub fn capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+    }
+
+    /// Sets the most texture memory kept at once, in bytes
+    pub fn texture_budget(&mut self, bytes: usize) {
+        self.texture_budget = bytes;
+    }
+
+    /// Adds a map, making it the most recently used one
+    pub fn push(&mut self, name: String, map: Map<T>) {
+        self.maps.insert(name.clone(), map);
+        self.touch(&name);
+    }
+
+    /// Marks a map as the most recently used one
+    pub fn touch(&mut self, name: &str) {
+        self.order.retain(|held| held != name);
+        if self.maps.contains_key(name) {
+            self.order.push_back(name.to_string());
+        }
+    }
+
+    /// Drops a map from the buffer, freeing its textures
+    pub fn remove(&mut self, name: &str) -> Option<Map<T>> {
+        self.order.retain(|held| held != name);
+        self.maps.remove(name)
+    }
+
+    /// Drops the least recently used maps until the buffer is back under its
+    /// capacity and texture budget. The maps named in keep are never dropped.
+    /// Returns the names of the maps that were dropped
+    pub fn evict(&mut self, keep: &[&str]) -> Vec<String> {
+        let mut evicted = vec![];
+
+        while self.maps.len() > self.capacity || self.get_texture_bytes() > self.texture_budget {
+            let oldest = match self
+                .order
+                .iter()
+                .find(|held| !keep.contains(&held.as_str()))
+            {
+                Some(name) => name.clone(),
+                None => break,
+            };
+
+            self.remove(&oldest);
+            evicted.push(oldest);
+        }
+
+        evicted
+    }
+}
+
+/// Loads maps on demand by name and moves the player between them
+pub struct World<T = Texture> {
+    // where map names are looked up
+    directory: PathBuf,
+    maps: MapBuffer<T>,
+    current: Option<String>,
+}
+
+impl<T> World<T> {
+    /// Creates a world that loads maps from directory and keeps at most
+    /// capacity of them, using no more than texture_budget bytes of textures
+    pub fn new(directory: PathBuf, capacity: usize, texture_budget: usize) -> World<T> {
+        World {
+            directory,
+            maps: MapBuffer::new(capacity, texture_budget),
+            current: None,
+        }
+    }
+
+    // Getters
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn get_map_buffer(&self) -> &MapBuffer<T> {
+        &self.maps
+    }
+
+    pub fn get_mut_map_buffer(&mut self) -> &mut MapBuffer<T> {
+        &mut self.maps
+    }
+
+    /// Returns the name of the map the player is on
+    pub fn get_current_name(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// Returns the map the player is on
+    pub fn get_current(&self) -> Option<&Map<T>> {
+        self.current.as_ref().and_then(|name| self.maps.get(name))
+    }
+
+    pub fn get_mut_current(&mut self) -> Option<&mut Map<T>> {
+        match self.current {
+            Some(ref name) => self.maps.get_mut(name),
+            None => None,
+        }
+    }
+}
+
+impl<T> World<T> {
+    /// Returns a map, loading it if it is not in the buffer yet
+    pub fn load<L>(&mut self, name: &str, loader: &mut L) -> Result<&Map<T>, String>
+    where
+        L: TextureLoader<Texture = T> + ?Sized,
+    {
+        if !self.maps.contains(name) {
+            let map = Map::open(&self.directory.join(name), loader)?;
+            self.maps.push(name.to_string(), map);
+        } else {
+            self.maps.touch(name);
+        }
+
+        // neither the map that was just loaded nor the one the player is on
+        // can be evicted to make room
+        match self.current {
+            Some(ref current) => self.maps.evict(&[name, current]),
+            None => self.maps.evict(&[name]),
+        };
+
+        Ok(self.maps.get(name).unwrap())
+    }
+
+    /// Makes a map the one the player is on, loading it if needed
+    pub fn enter<L>(&mut self, name: &str, loader: &mut L) -> Result<&Map<T>, String>
+    where
+        L: TextureLoader<Texture = T> + ?Sized,
+    {
+        self.load(name, loader)?;
+        self.current = Some(name.to_string());
+
+        // the map that was left behind can go now if there is no room for it
+        self.maps.evict(&[name]);
+
+        Ok(self.get_current().unwrap())
+    }
+
+    /// Returns the warp under a point on the current map
+    pub fn warp_at(&self, x: f64, y: f64) -> Option<&Warp> {
+        self.get_current()
+            .and_then(|map| map.get_objects().warp_at(x, y))
+    }
+
+    /// Takes the player through a warp. Returns where on the new map the
+    /// player should be placed, in pixels
+    pub fn warp<L>(&mut self, warp: &Warp, loader: &mut L) -> Result<(f64, f64), String>
+    where
+        L: TextureLoader<Texture = T> + ?Sized,
+    {
+        let map = self.enter(warp.get_map(), loader)?;
+
+        match map.get_objects().get_spawn(warp.get_spawn()) {
+            Some(spawn) => Ok(spawn.get_position()),
+            None => Err(format!(
+                "{} has no spawn named {:?}",
+                warp.get_map(),
+                warp.get_spawn()
+            )),
+        }
+    }
+
+    /// Warps the player if they are standing in a warp on the current map
+    pub fn travel<L>(
+        &mut self,
+        x: f64,
+        y: f64,
+        loader: &mut L,
+    ) -> Option<Result<(f64, f64), String>>
+    where
+        L: TextureLoader<Texture = T> + ?Sized,
+    {
+        let warp = self.warp_at(x, y).cloned()?;
+        Some(self.warp(&warp, loader))
+    }
+
+    /// Advances the current map's clock by dt seconds
+    pub fn update(&mut self, dt: f64) {
+        if let Some(map) = self.get_mut_current() {
+            map.update(dt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    use image::RgbaImage;
+    use render::SoftwareLoader;
+
+    /// Counts the textures loaded so tests can tell when a map was cached
+    struct CountingLoader {
+        loads: usize,
+    }
+
+    impl TextureLoader for CountingLoader {
+        type Texture = RgbaImage;
+
+        fn load_texture(&mut self, path: &Path) -> Result<RgbaImage, String> {
+            self.loads += 1;
+            SoftwareLoader.load_texture(path)
+        }
+
+        fn load_image(&mut self, image: &RgbaImage) -> Result<RgbaImage, String> {
+            self.loads += 1;
+            SoftwareLoader.load_image(image)
+        }
+    }
+
+    fn world(capacity: usize, texture_budget: usize) -> World<RgbaImage> {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/maps");
+        World::new(fixtures, capacity, texture_budget)
+    }
+
+    #[test]
+    fn maps_are_cached() {
+        let mut world = world(4, usize::MAX);
+        let loader = &mut CountingLoader { loads: 0 };
+
+        world.enter("town.tmx", loader).unwrap();
+        world.enter("house.tmx", loader).unwrap();
+        world.enter("town.tmx", loader).unwrap();
+
+        assert_eq!(loader.loads, 2);
+        assert_eq!(world.get_current_name(), Some("town.tmx"));
+        let names: Vec<&String> = world.get_map_buffer().get_names().collect();
+        assert_eq!(names, vec!["house.tmx", "town.tmx"]);
+    }
+
+    #[test]
+    fn warps_move_between_maps() {
+        let mut world = world(4, usize::MAX);
+        let loader = &mut SoftwareLoader;
+
+        world.enter("town.tmx", loader).unwrap();
+        assert!(world.warp_at(8.0, 8.0).is_none());
+
+        // the door of the house
+        let spawn = world.travel(72.0, 8.0, loader).unwrap().unwrap();
+        assert_eq!(world.get_current_name(), Some("house.tmx"));
+        assert_eq!(spawn, (32.0, 48.0));
+
+        // and back out again
+        let spawn = world.travel(36.0, 72.0, loader).unwrap().unwrap();
+        assert_eq!(world.get_current_name(), Some("town.tmx"));
+        assert_eq!(spawn, (64.0, 16.0));
+    }
+
+    #[test]
+    fn least_recently_used_maps_are_evicted() {
+        let mut world = world(2, usize::MAX);
+        let loader = &mut SoftwareLoader;
+
+        world.enter("town.tmx", loader).unwrap();
+        world.load("house.tmx", loader).unwrap();
+        world.enter("town.tmx", loader).unwrap();
+        world.load("cave.tmx", loader).unwrap();
+
+        // the house was used longest ago, the town is current
+        assert!(!world.get_map_buffer().contains("house.tmx"));
+        assert!(world.get_map_buffer().contains("town.tmx"));
+        assert!(world.get_map_buffer().contains("cave.tmx"));
+    }
+
+    #[test]
+    fn maps_are_evicted_over_texture_budget() {
+        // each fixture map uses one 600x432 texture
+        let one_map = 600 * 432 * 4;
+        let mut world = world(8, one_map);
+        let loader = &mut SoftwareLoader;
+
+        world.enter("town.tmx", loader).unwrap();
+        world.enter("house.tmx", loader).unwrap();
+
+        assert_eq!(world.get_map_buffer().len(), 1);
+        assert_eq!(world.get_map_buffer().get_texture_bytes(), one_map);
+        assert!(world.get_current().is_some());
+    }
+
+    #[test]
+    fn missing_maps_are_errors() {
+        let mut world = world(2, usize::MAX);
+
+        assert!(world.enter("nowhere.tmx", &mut SoftwareLoader).is_err());
+        assert!(world.get_current().is_none());
+    }
+}